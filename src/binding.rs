@@ -8,6 +8,8 @@ pub(crate) const INFINITE: u32 = 0xFFFFFFFF;
 pub(crate) const WAIT_OBJECT_0: u32 = 0x00000000;
 // https://learn.microsoft.com/en-us/windows/win32/api/processthreadsapi/nf-processthreadsapi-getexitcodeprocess#remarks
 pub(crate) const STATUS_PENDING: u32 = 0x00000103;
+// https://learn.microsoft.com/en-us/windows/win32/procthread/process-creation-flags#flags
+pub(crate) const CREATE_UNICODE_ENVIRONMENT: u32 = 0x00000400;
 
 // https://learn.microsoft.com/en-us/windows/win32/winprog/windows-data-types#BOOL
 pub(crate) type BOOL = i32;
@@ -18,7 +20,7 @@ pub(crate) type PCWSTR = *const u16;
 // https://learn.microsoft.com/en-us/windows/win32/winprog/windows-data-types#PDWORD
 pub(crate) type PDWORD = *mut u32;
 // https://learn.microsoft.com/en-us/windows/win32/winprog/windows-data-types#PVOID
-type PVOID = *mut c_void;
+pub(crate) type PVOID = *mut c_void;
 // https://learn.microsoft.com/en-us/windows/win32/winprog/windows-data-types#PWSTR
 pub(crate) type PWSTR = *mut u16;
 // https://learn.microsoft.com/en-us/windows/win32/winprog/windows-data-types#UINT
@@ -1,55 +1,675 @@
 use crate::dependency::{parse_dependency, Dependency};
+use anyhow::{bail, ensure, Result};
+use regex::Regex;
+use std::{path::PathBuf, sync::LazyLock};
 
 /// This tool allow you to create a new Rust temporary project in a temporary
 /// directory.
 ///
 /// The dependencies can be provided in arguments (e.g.`cargo-temp anyhow
-/// tokio`). When the shell is exited, the temporary directory is deleted unless
+/// tokio`). If the first argument is an existing `.rs` file, it is used as
+/// `src/main.rs`. When the shell is exited, the temporary directory is deleted unless
 /// you removed the file `TO_DELETE`.
 #[derive(clap::Parser, Debug, Clone)]
-#[command(author, version, about, long_about)]
+#[command(
+    author,
+    version,
+    about,
+    long_about,
+    group(clap::ArgGroup::new("worktree").args(["worktree_branch", "worktree_new", "worktree_at"]))
+)]
 pub struct Cli {
+    /// Print the path and the content of the config file.
+    #[arg(long, exclusive = true)]
+    pub config: bool,
+
+    /// Print the path of the config file.
+    #[arg(long, exclusive = true)]
+    pub config_path: bool,
+
+    /// List the projects in the temporary and preserved project directories.
+    #[arg(long, exclusive = true)]
+    pub list: bool,
+
+    /// Print the files changed since the snapshot of a project.
+    ///
+    /// The project must have been created with `--snapshot`. It's in the
+    /// current directory by default.
+    #[arg(long, value_name = "PATH", num_args = 0..=1, exclusive = true)]
+    pub diff: Option<Option<PathBuf>>,
+
     /// Dependencies to add to `Cargo.toml`.
     ///
     /// The default version used is `*` but this can be replaced using `=`.
     /// E.g. `cargo-temp anyhow=1.0.13`
-    #[arg(value_parser = parse_dependency)]
+    ///
+    /// Use the `%dev` or `%build` suffix to add a dev or build dependency.
+    /// E.g. `cargo-temp proptest%dev`
+    #[arg(value_name = "DEPENDENCIES")]
+    pub dependency_args: Vec<String>,
+
+    /// Dependencies parsed from the arguments, the invalid ones are skipped
+    /// or refused depending on the `skip_invalid_deps` setting.
+    #[arg(skip)]
     pub dependencies: Vec<Dependency>,
 
+    /// Add the dependencies of a `Cargo.toml` snippet.
+    ///
+    /// The `[dependencies]`, `[dev-dependencies]` and `[build-dependencies]`
+    /// tables are read. Use `-` to read the snippet from the standard input.
+    #[arg(long, value_name = "TOML")]
+    pub from_manifest: Option<String>,
+
+    /// Add the dependencies of a file.
+    ///
+    /// The file is either a `Cargo.toml` snippet with dependency tables or a
+    /// list of dependencies, one per line.
+    #[arg(long, value_name = "PATH")]
+    pub deps_file: Option<PathBuf>,
+
+    /// Don't add the `default_dependencies` from the config file.
+    #[arg(long)]
+    pub no_default_deps: bool,
+
+    /// Add the dependencies of a template from the config file, can be used
+    /// multiple times.
+    #[arg(long, short = 't', value_name = "NAME")]
+    pub template: Vec<String>,
+
+    /// Create the files of a scaffold from the config file, can be used
+    /// multiple times.
+    #[arg(long, value_name = "NAME")]
+    pub scaffold: Vec<String>,
+
+    /// Log each dependency line added to `Cargo.toml`.
+    #[arg(long)]
+    pub verbose_deps: bool,
+
+    /// Run cargo without accessing the network.
+    ///
+    /// `CARGO_NET_OFFLINE=true` is set for cargo, the shell and the
+    /// subprocesses. This overrides the `offline` setting. Only a local
+    /// repository can be cloned with `--git`.
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Download the dependencies with `cargo fetch` before starting the
+    /// shell.
+    ///
+    /// This is ignored when running offline.
+    #[arg(long, visible_alias = "fetch")]
+    pub prefetch: bool,
+
+    /// Build the project with `cargo build` before starting the shell.
+    ///
+    /// The shell is started even if the build fails. This overrides the
+    /// `always_build` setting.
+    #[arg(long)]
+    pub build: bool,
+
     /// Create a library instead of a binary.
     #[arg(long, short = 'l')]
     pub lib: bool,
 
+    /// Create a library with a `src/main.rs` using it.
+    #[arg(long, conflicts_with_all = ["lib", "no_std", "git", "worktree"])]
+    pub lib_with_bin: bool,
+
+    /// Add a `build.rs` build script to the project.
+    ///
+    /// With build-dependencies, it contains a commented example using
+    /// `cc::Build`.
+    #[arg(long, conflicts_with_all = ["git", "worktree"])]
+    pub build_script: bool,
+
     /// Name of the temporary crate.
-    #[arg(long = "name", short = 'n')]
+    ///
+    /// It must be a valid package name for cargo. It's also the name of the
+    /// crate and its binary unless `--bin-name` is provided.
+    #[arg(long = "name", short = 'n', value_parser = parse_project_name)]
     pub project_name: Option<String>,
 
+    /// Name of the crate and its binary, passed to `cargo init --name`.
+    ///
+    /// It takes precedence over `--name` for the crate, which still names the
+    /// project's directory.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_parser = parse_project_name,
+        conflicts_with_all = ["lib", "git", "worktree"],
+    )]
+    pub bin_name: Option<String>,
+
+    /// Refuse to create the project if a preserved project already has its
+    /// name.
+    #[arg(long, requires = "project_name")]
+    pub exact_name: bool,
+
     /// Create a temporary Git working tree based on the repository in the
     /// current directory.
     #[arg(long = "worktree", short = 'w')]
     pub worktree_branch: Option<Option<String>>,
 
+    /// Create a temporary Git working tree with a new branch at the current
+    /// HEAD.
+    ///
+    /// When the project is deleted, the branch is deleted too with `--delete`.
+    /// Otherwise, you're asked whether it should be deleted if the `prompt`
+    /// setting is enabled, and it is kept if not.
+    #[arg(long, value_name = "BRANCH")]
+    pub worktree_new: Option<String>,
+
+    /// Create a detached temporary Git working tree at a commit.
+    #[arg(long, value_name = "COMMIT")]
+    pub worktree_at: Option<String>,
+
+    /// Repository of the working tree created with `--worktree`.
+    ///
+    /// The default is the repository in the current directory.
+    #[arg(long, value_name = "PATH", requires = "worktree")]
+    pub repo: Option<PathBuf>,
+
     /// Create a temporary clone of a Git repository.
     #[arg(long, short = 'g')]
     pub git: Option<String>,
 
+    /// Clone only this branch of the repository given with `--git`.
+    ///
+    /// `--branch <BRANCH> --single-branch` is passed to `git clone`. This is
+    /// ignored with `--worktree`.
+    #[arg(long, requires = "git")]
+    pub branch: Option<String>,
+
+    /// Check out this commit of the repository given with `--git`.
+    ///
+    /// The commit is fetched if it isn't part of the clone, e.g. because of
+    /// the `git_repo_depth` setting.
+    #[arg(long, value_name = "REV", requires = "git")]
+    pub git_rev: Option<String>,
+
+    /// Fetch the whole history of the repository given with `--git` after
+    /// the shallow clone.
+    ///
+    /// This needs access to the network.
+    #[arg(long, requires = "git")]
+    pub unshallow: bool,
+
+    /// Clone the submodules of the repository given with `--git`.
+    ///
+    /// This overrides the `git_clone_submodules` setting.
+    #[arg(long, requires = "git")]
+    pub recurse_submodules: bool,
+
+    /// Partial clone filter of the repository given with `--git`, e.g.
+    /// `blob:none`.
+    ///
+    /// This overrides the `git_clone_filter` setting.
+    #[arg(long, value_name = "SPEC", requires = "git")]
+    pub filter: Option<String>,
+
+    /// Check out only this directory of the repository given with `--git`,
+    /// can be used multiple times.
+    ///
+    /// The files at the root of the repository are always checked out.
+    #[arg(long, value_name = "PATH", requires = "git")]
+    pub sparse: Vec<String>,
+
+    /// Create and check out a new branch in the repository given with
+    /// `--git`.
+    ///
+    /// The branch starts from the cloned commit, or from `--git-rev`.
+    #[arg(long, value_name = "BRANCH", requires = "git")]
+    pub new_branch: Option<String>,
+
+    /// Apply a patch file to the project once it is created.
+    ///
+    /// The patch is applied with `git apply`, or `patch -p1` when the project
+    /// is not a git repository. This can be repeated to apply several patches
+    /// in order.
+    #[arg(long, value_name = "PATCH")]
+    pub apply: Vec<PathBuf>,
+
+    /// Use this snippet as `src/main.rs`.
+    ///
+    /// The snippet is wrapped in a `main` function if it doesn't contain one.
+    /// E.g. `cargo-temp --code 'dbg!(std::mem::size_of::<Option<Box<u8>>>());'`
+    #[arg(long, conflicts_with_all = ["lib", "worktree", "git"])]
+    pub code: Option<String>,
+
+    /// Copy a Rust file or the content of a directory into the project.
+    ///
+    /// A `.rs` file replaces `src/main.rs` (or `src/lib.rs` with `--lib`), a
+    /// directory is merged into the project without its `target` and `.git`
+    /// directories.
+    #[arg(
+        long,
+        value_name = "FILE_OR_DIR",
+        conflicts_with_all = ["code", "git", "worktree"]
+    )]
+    pub copy: Option<PathBuf>,
+
+    /// Rust file given as first argument, copied to `src/main.rs`.
+    #[arg(skip)]
+    pub script: Option<PathBuf>,
+
+    /// VCS to initialize in the temporary project.
+    ///
+    /// This overrides the `vcs` setting of the config file.
+    #[arg(
+        long,
+        value_parser = ["git", "hg", "pijul", "fossil", "none"],
+        conflicts_with_all = ["worktree", "git"],
+    )]
+    pub vcs: Option<String>,
+
+    /// Don't initialize any VCS in the temporary project.
+    ///
+    /// This overrides the `vcs` setting of the config file.
+    #[arg(long, conflicts_with_all = ["worktree", "git", "vcs"])]
+    pub no_git: bool,
+
+    /// Cargo's target directory for this project.
+    ///
+    /// This overrides the `cargo_target_dir` setting and the
+    /// `CARGO_TARGET_DIR` environment variable.
+    #[arg(long)]
+    pub target_dir: Option<PathBuf>,
+
+    /// Shell to start in the temporary project.
+    ///
+    /// This overrides the `shell` setting and the `SHELL` (or `COMSPEC`)
+    /// environment variable.
+    #[arg(long)]
+    pub shell: Option<String>,
+
+    /// Start the shell with a minimal environment.
+    ///
+    /// Only a few essential variables are kept and `PATH` is reset to the
+    /// directories of cargo and rustc and the system directories, or to the
+    /// `clean_env_path` setting. This also applies to the editor and the
+    /// terminal command.
+    #[arg(long)]
+    pub clean_env: bool,
+
+    /// Editor to open the temporary project with.
+    ///
+    /// This overrides the `editor` and `editor_args` settings.
+    #[arg(long)]
+    pub editor: Option<String>,
+
+    /// Argument passed to the editor, can be used multiple times.
+    ///
+    /// This overrides the `editor_args` setting.
+    #[arg(long = "editor-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub editor_args: Vec<String>,
+
+    /// Start a shell even if an editor is configured.
+    #[arg(long, conflicts_with_all = ["editor", "editor_args"])]
+    pub no_editor: bool,
+
+    /// Open the project with a terminal command instead of the shell.
+    ///
+    /// `{name}` is replaced by the project's name and `{dir}` by its path,
+    /// e.g. `--open 'tmux new-session -s {name} -c {dir}'`. This overrides
+    /// the `editor` and `terminal_command` settings.
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["editor", "no_shell"])]
+    pub open: Option<String>,
+
+    /// Don't start the subprocesses from the config file.
+    #[arg(long)]
+    pub no_subprocesses: bool,
+
+    /// Start a background subprocess with the shell, can be used multiple
+    /// times.
+    ///
+    /// The subprocess is added to the ones from the config file and is
+    /// killed when exiting the shell.
+    #[arg(long, value_name = "COMMAND", conflicts_with = "no_shell")]
+    pub exec: Vec<String>,
+
+    /// Run a foreground subprocess before the shell starts, can be used
+    /// multiple times.
+    #[arg(long, value_name = "COMMAND", conflicts_with = "no_shell")]
+    pub exec_fg: Vec<String>,
+
+    /// Create the project and print its path without starting a shell.
+    ///
+    /// No `TO_DELETE` file is created and no subprocess is started, the
+    /// project is left in place.
+    #[arg(long)]
+    pub no_shell: bool,
+
+    /// Run the project with `cargo run` instead of starting a shell.
+    ///
+    /// The project is deleted afterwards unless `--keep` is used and
+    /// cargo-temp exits with the exit code of the binary.
+    #[arg(
+        long,
+        conflicts_with_all = ["no_shell", "lib", "worktree", "archive"],
+    )]
+    pub run: bool,
+
+    /// Use the release profile for `--build` and `--run`.
+    ///
+    /// It requires `--build`, `--run`, `--bench` or the `always_build`
+    /// setting.
+    #[arg(long)]
+    pub release: bool,
+
+    /// Arguments passed to `cargo run`, after `--`.
+    ///
+    /// E.g. `cargo-temp --run --git <URL> -- --features foo -- --help`
+    #[arg(last = true, value_name = "CARGO_RUN_ARGS", requires = "run")]
+    pub run_args: Vec<String>,
+
+    /// Only print cargo-temp's warnings and errors on stderr and don't show
+    /// the welcome message.
+    ///
+    /// All the logs are still written to the `log_file` if set.
+    #[arg(long, short = 'q')]
+    pub quiet: bool,
+
+    /// Format of the informational messages.
+    ///
+    /// With `json`, a single JSON object with the project's path, its crate
+    /// name, its dependencies and whether it has been preserved is printed
+    /// to stdout once the project is left (to stderr with `--run`).
+    #[arg(long, value_enum, default_value_t, conflicts_with = "dry_run")]
+    pub format: Format,
+
+    /// Record the hashes of the project's files once it's created.
+    ///
+    /// The files added, modified and removed during the session are printed
+    /// when exiting the shell. The snapshot is kept in the project, use
+    /// `cargo-temp --diff` to compare it with the files later.
+    #[arg(long)]
+    pub snapshot: bool,
+
+    /// Print what would be created without touching the filesystem.
+    ///
+    /// This prints the temporary directory, the command creating the
+    /// project, the dependencies added to `Cargo.toml` and the subprocesses
+    /// that would start.
+    #[arg(long)]
+    pub dry_run: bool,
+
     /// Add a `benches` to the temporary project.
     ///
     /// You can choose the name of the benchmark file name as argument.
-    /// The default is `benchmark.rs`
-    #[arg(long, short = 'b')]
-    pub bench: Option<Option<String>>,
+    /// The default is `benchmark.rs`. This can be repeated to add several
+    /// benchmarks.
+    #[arg(long, short = 'b', num_args = 0..=1, value_name = "NAME")]
+    pub bench: Option<Vec<String>>,
 
-    /// Select the Rust's edition of the temporary project.
+    /// Generate a `#![no_std]` project.
     ///
-    /// Available options are:
-    /// * 15 | 2015 => edition 2015,
-    /// * 18 | 2018 => edition 2018,
-    /// * 21 | 2021 => edition 2021,
+    /// A binary also gets `#![no_main]`, a panic handler and `panic = "abort"`
+    /// in its profiles. You can give a panic handler crate as argument (e.g.
+    /// `--no-std=panic-halt`), it is added as a dependency and used instead
+    /// of the generated panic handler.
     ///
-    /// If the argument doesn't match any of the options, the default is the latest edition
+    /// A binary can't be linked on the host, it can only be built with a
+    /// `no_std` target given with `--target`.
+    #[arg(
+        long,
+        value_name = "PANIC_CRATE",
+        value_parser = parse_dependency,
+        require_equals = true,
+        conflicts_with_all = ["bench", "code", "git", "worktree", "run"],
+    )]
+    pub no_std: Option<Option<Dependency>>,
+
+    /// Copy a `Cargo.lock` into the project to pin the versions of the
+    /// dependencies.
+    #[arg(long, value_name = "PATH")]
+    pub reuse_lock: Option<PathBuf>,
+
+    /// Pass `--locked` to cargo to make sure the `Cargo.lock` isn't updated.
+    ///
+    /// This applies to `--fetch`, `--build`, `--run` and the background
+    /// check.
+    #[arg(long)]
+    pub locked: bool,
+
+    /// Build for the target triple by default.
+    ///
+    /// This writes the target to the project's `.cargo/config.toml`.
+    #[arg(long, value_name = "TRIPLE")]
+    pub target: Option<String>,
+
+    /// Pin the Rust toolchain of the project with a `rust-toolchain.toml`.
+    ///
+    /// The channel is `stable`, `beta` or `nightly`, optionally with a date
+    /// (e.g. `nightly-2024-01-01`), or a version number (e.g. `1.75.0`).
+    #[arg(long, value_name = "CHANNEL", value_parser = parse_toolchain)]
+    pub toolchain: Option<String>,
+
+    /// Add the lint levels of a preset to the `[lints]` table of `Cargo.toml`.
+    ///
+    /// `strict` is built in, other presets are defined with the `lints`
+    /// setting. The lints are merged with the existing ones of a cloned
+    /// repository.
+    #[arg(long, value_name = "PRESET")]
+    pub lints: Option<String>,
+
+    /// Set the description of the package in `Cargo.toml` and write it in a
+    /// `README.md`.
+    ///
+    /// An existing `README.md` is kept unless `--replace-readme` is used.
+    #[arg(long, value_name = "TEXT")]
+    pub description: Option<String>,
+
+    /// Replace the existing `README.md` with the one of `--description`.
+    #[arg(long, requires = "description")]
+    pub replace_readme: bool,
+
+    /// Select the Rust's edition of the temporary project.
+    ///
+    /// The edition is passed to `cargo init`, two digits are a short form
+    /// (e.g. `21` for 2021).
     #[arg(long, short = 'e')]
-    pub edition: Option<u32>,
+    pub edition: Option<String>,
+
+    /// Preserve the project when exiting the shell.
+    ///
+    /// No `TO_DELETE` file is created.
+    #[arg(long, short = 'k', conflicts_with = "no_shell")]
+    pub keep: bool,
+
+    /// Directory where the project is preserved.
+    ///
+    /// The directory is created if it doesn't exist. This overrides the
+    /// `preserved_project_dir` setting.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["no_shell", "archive"])]
+    pub preserve_to: Option<PathBuf>,
+
+    /// Delete the project without confirmation when exiting the shell.
+    ///
+    /// This skips the prompt enabled by the `prompt` setting. The project is
+    /// still preserved if the `TO_DELETE` file has been removed.
+    #[arg(long, visible_alias = "yes", short = 'y', conflicts_with = "keep")]
+    pub delete: bool,
+
+    /// Archive the project instead of preserving its directory.
+    ///
+    /// When the project is kept, a gzipped tarball of the project is written
+    /// to this path, without the `target` directory, and the project is
+    /// deleted.
+    #[arg(long, value_name = "PATH.tar.gz", conflicts_with = "no_shell")]
+    pub archive: Option<PathBuf>,
+
+    /// Proceed even if the number of dependencies exceeds the
+    /// `max_dependencies` setting.
+    #[arg(long)]
+    pub force: bool,
+
+    /// Create the quickest possible scratch project.
+    ///
+    /// This is the same as setting `vcs = "none"`, `welcome_message = false`
+    /// and `prompt = false` in the config file for this run. The project is
+    /// always a binary.
+    #[arg(long, conflicts_with = "lib")]
+    pub bare: bool,
+}
+
+impl Cli {
+    /// Returns the command of `--config`, `--config-path`, `--list` or `--diff`, which runs instead
+    /// of creating a project.
+    pub fn action(&self) -> Option<Command> {
+        if self.config || self.config_path {
+            Some(Command::Config {
+                path: self.config_path,
+            })
+        } else if self.list {
+            Some(Command::List)
+        } else {
+            self.diff.clone().map(|path| Command::Diff { path })
+        }
+    }
+
+    /// Returns `true` if the project is a temporary Git working tree.
+    pub fn worktree(&self) -> bool {
+        self.worktree_branch.is_some() || self.worktree_new.is_some() || self.worktree_at.is_some()
+    }
+
+    /// `--release` requires `--build`, `--run` or `--bench`, unless the `always_build` setting
+    /// builds the project anyway.
+    ///
+    /// The setting is only known once the config file is read, the error is reported like the
+    /// ones of the arguments.
+    pub fn check_release(&self, always_build: bool) -> Result<(), clap::Error> {
+        use clap::CommandFactory;
+
+        if self.release && !(self.build || self.run || self.bench.is_some() || always_build) {
+            return Err(Cli::command().error(
+                clap::error::ErrorKind::MissingRequiredArgument,
+                "the argument '--release' requires '--build', '--run', '--bench' or the \
+                `always_build` setting",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Commands of `--config`, `--config-path`, `--list` and `--diff`.
+///
+/// They are options rather than subcommands, so `cargo-temp diff` still adds the `diff` crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Print the path and the content of the config file, or only its path.
+    Config { path: bool },
+    /// List the projects in the temporary and preserved project directories.
+    List,
+    /// Print the files changed since the snapshot of a project.
+    Diff { path: Option<PathBuf> },
+}
+
+/// Names that cargo refuses as package names, in addition to the Rust keywords.
+const RESERVED_NAMES: &[&str] = &[
+    "alloc",
+    "build",
+    "core",
+    "deps",
+    "examples",
+    "incremental",
+    "proc-macro",
+    "proc_macro",
+    "std",
+    "test",
+];
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Validate the project name against the rules of cargo for package names.
+fn parse_project_name(name: &str) -> Result<String> {
+    let suggestion = || {
+        let sanitized = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect::<String>();
+
+        if sanitized.is_empty() {
+            "my-project".to_string()
+        } else if sanitized.starts_with(|c: char| c.is_ascii_digit())
+            || RESERVED_NAMES.contains(&sanitized.as_str())
+            || KEYWORDS.contains(&sanitized.as_str())
+        {
+            format!("my-{sanitized}")
+        } else {
+            sanitized
+        }
+    };
+
+    if name.is_empty() {
+        bail!("the name cannot be empty, try `{}`", suggestion());
+    }
+
+    if name.contains(['/', '\\']) {
+        bail!(
+            "the name cannot contain a path separator, try `{}`",
+            suggestion()
+        );
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '_' && *c != '-')
+    {
+        bail!(
+            "invalid character `{c}` in the name, only letters, digits, `_` and `-` are \
+            allowed, try `{}`",
+            suggestion()
+        );
+    }
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        bail!("the name cannot start with a digit, try `{}`", suggestion());
+    }
+
+    if RESERVED_NAMES.contains(&name) || KEYWORDS.contains(&name) {
+        bail!(
+            "`{name}` is reserved and cannot be used as a name, try `{}`",
+            suggestion()
+        );
+    }
+
+    Ok(name.to_string())
+}
+
+/// Validate a toolchain channel as accepted by rustup.
+fn parse_toolchain(channel: &str) -> Result<String> {
+    static RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^((stable|beta|nightly)(-\d{4}-\d{2}-\d{2})?|\d+\.\d+(\.\d+)?)$").unwrap()
+    });
+
+    ensure!(
+        RE.is_match(channel),
+        "invalid toolchain `{channel}`, expected `stable`, `beta`, `nightly`, a dated channel \
+        like `nightly-2024-01-01` or a version like `1.75.0`"
+    );
+
+    Ok(channel.to_string())
 }
 
 #[cfg(test)]
@@ -61,4 +681,113 @@ mod tests {
         use clap::CommandFactory;
         Cli::command().debug_assert();
     }
+
+    #[test]
+    fn benches() {
+        use clap::Parser;
+
+        let cli = Cli::parse_from(["cargo-temp", "--bench"]);
+        assert_eq!(cli.bench, Some(Vec::new()));
+
+        let cli = Cli::parse_from(["cargo-temp", "--bench", "foo", "-b", "bar"]);
+        assert_eq!(cli.bench, Some(vec!["foo".to_string(), "bar".to_string()]));
+    }
+
+    #[test]
+    fn commands() {
+        use clap::Parser;
+
+        for name in ["config", "list", "diff"] {
+            let cli = Cli::parse_from(["cargo-temp", name]);
+            assert_eq!(cli.action(), None);
+            assert_eq!(cli.dependency_args, [name]);
+        }
+
+        let cli = Cli::parse_from(["cargo-temp", "--config-path"]);
+        assert_eq!(cli.action(), Some(Command::Config { path: true }));
+
+        let cli = Cli::parse_from(["cargo-temp", "--diff", "path/to/project"]);
+        assert_eq!(
+            cli.action(),
+            Some(Command::Diff {
+                path: Some(PathBuf::from("path/to/project"))
+            })
+        );
+
+        let cli = Cli::parse_from(["cargo-temp", "--diff"]);
+        assert_eq!(cli.action(), Some(Command::Diff { path: None }));
+
+        assert!(Cli::try_parse_from(["cargo-temp", "--list", "anyhow"]).is_err());
+    }
+
+    #[test]
+    fn release() {
+        use clap::Parser;
+
+        let cli = Cli::parse_from(["cargo-temp", "--release"]);
+        assert!(cli.check_release(false).is_err());
+        assert!(cli.check_release(true).is_ok());
+
+        for flag in ["--build", "--run", "--bench"] {
+            let cli = Cli::parse_from(["cargo-temp", "--release", flag]);
+            assert!(cli.check_release(false).is_ok(), "{flag}");
+        }
+    }
+
+    #[test]
+    fn no_std() {
+        use clap::Parser;
+
+        let cli = Cli::parse_from(["cargo-temp", "--no-std", "heapless"]);
+        assert_eq!(cli.no_std, Some(None));
+        assert_eq!(cli.dependency_args, ["heapless"]);
+
+        let cli = Cli::parse_from(["cargo-temp", "--no-std=panic-halt", "heapless"]);
+        assert_eq!(
+            cli.no_std.flatten().map(|x| x.name().to_string()),
+            Some("panic-halt".to_string())
+        );
+        assert_eq!(cli.dependency_args, ["heapless"]);
+    }
+
+    #[test]
+    fn toolchain() {
+        for channel in [
+            "stable",
+            "nightly",
+            "nightly-2024-01-01",
+            "beta",
+            "1.75.0",
+            "1.75",
+        ] {
+            assert_eq!(parse_toolchain(channel).unwrap(), channel);
+        }
+
+        for channel in [
+            "",
+            "1",
+            "night",
+            "nightly-2024",
+            "1.75.0-x86_64-unknown-linux-gnu",
+        ] {
+            assert!(parse_toolchain(channel).is_err(), "{channel}");
+        }
+    }
+
+    #[test]
+    fn project_name() {
+        assert_eq!(parse_project_name("my_app-2").unwrap(), "my_app-2");
+
+        for (name, suggestion) in [
+            ("", "my-project"),
+            ("my proj", "my-proj"),
+            ("a/b", "a-b"),
+            ("1foo", "my-1foo"),
+            ("test", "my-test"),
+            ("fn", "my-fn"),
+        ] {
+            let err = parse_project_name(name).unwrap_err().to_string();
+            assert!(err.ends_with(&format!("try `{suggestion}`")), "{err}");
+        }
+    }
 }
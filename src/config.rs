@@ -1,33 +1,115 @@
 use crate::subprocess::SubProcess;
 use anyhow::{Context, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub welcome_message: bool,
     #[serde(default)]
+    pub welcome_message_text: Option<String>,
+    #[serde(default)]
     pub cargo_target_dir: Option<PathBuf>,
     #[serde(default)]
     pub preserved_project_dir: Option<PathBuf>,
     #[serde(default)]
     pub prompt: bool,
     #[serde(default)]
+    pub keep_by_default: bool,
+    #[serde(default)]
+    pub generate_keep_script: bool,
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub clean_env: bool,
+    #[serde(default)]
+    pub clean_env_path: Option<String>,
+    #[serde(default)]
     pub editor: Option<String>,
     #[serde(default)]
     pub editor_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub use_env_editor: bool,
+    #[serde(default)]
+    pub terminal_command: Option<String>,
     pub temporary_project_dir: PathBuf,
     #[serde(default)]
+    pub temporary_project_dir_severity: Severity,
+    #[serde(default)]
+    pub tmp_prefix: Option<String>,
+    #[serde(default)]
+    pub worktree_prefix: Option<String>,
+    #[serde(default)]
     pub git_repo_depth: Option<Depth>,
     #[serde(default)]
+    pub git_clone_submodules: bool,
+    #[serde(default)]
+    pub git_clone_filter: Option<String>,
+    #[serde(default)]
+    pub git_unshallow: bool,
+    #[serde(default)]
     pub vcs: Option<String>,
+    #[serde(default)]
+    pub offline: bool,
+    #[serde(default)]
+    pub check_for_updates: bool,
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    #[serde(default)]
+    pub log_file_mode: LogFileMode,
+    #[serde(default)]
+    pub log_file_max_size: Option<u64>,
+    #[serde(default)]
+    pub log_file_max_old: Option<usize>,
+    #[serde(default)]
+    pub bench_fn_body: Option<String>,
+    #[serde(default)]
+    pub name_from_first_dependency: bool,
+    #[serde(default)]
+    pub dedup_strategy: DedupStrategy,
+    #[serde(default)]
+    pub login_shell: bool,
+    #[serde(default)]
+    pub post_create: Option<String>,
+    #[serde(default)]
+    pub post_create_required: bool,
+    #[serde(default)]
+    pub use_cargo_add: bool,
+    #[serde(default)]
+    pub always_build: bool,
+    #[serde(default)]
+    pub background_check: bool,
+    #[serde(default)]
+    pub max_dependencies: Option<usize>,
+    #[serde(default)]
+    pub manifest_wrap_width: Option<usize>,
+    #[serde(default)]
+    pub skip_invalid_deps: bool,
+    #[serde(default)]
+    pub max_dependencies_severity: Severity,
+    #[serde(default)]
+    pub extra_manifest: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub default_dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scaffolds: BTreeMap<String, Vec<ScaffoldFile>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lints: BTreeMap<String, toml::Table>,
     #[serde(default, rename = "subprocess", skip_serializing_if = "Vec::is_empty")]
     pub subprocesses: Vec<SubProcess>,
 }
 
 impl Config {
-    fn new() -> Result<Self> {
+    pub fn new() -> Result<Self> {
         #[cfg(unix)]
         let temporary_project_dir = {
             let cache_dir = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"))
@@ -42,38 +124,84 @@ impl Config {
 
         Ok(Self {
             welcome_message: true,
+            welcome_message_text: None,
             cargo_target_dir: None,
             preserved_project_dir: None,
             prompt: false,
+            keep_by_default: false,
+            generate_keep_script: false,
+            shell: None,
+            clean_env: false,
+            clean_env_path: None,
             editor: None,
             editor_args: None,
+            use_env_editor: false,
+            terminal_command: None,
             git_repo_depth: None,
+            git_clone_submodules: false,
+            git_clone_filter: None,
+            git_unshallow: false,
             temporary_project_dir,
+            temporary_project_dir_severity: Severity::default(),
+            tmp_prefix: None,
+            worktree_prefix: None,
             vcs: None,
+            offline: false,
+            check_for_updates: false,
+            log_file: None,
+            log_file_mode: LogFileMode::default(),
+            log_file_max_size: None,
+            log_file_max_old: None,
+            bench_fn_body: None,
+            name_from_first_dependency: false,
+            dedup_strategy: DedupStrategy::default(),
+            login_shell: false,
+            post_create: None,
+            post_create_required: false,
+            use_cargo_add: false,
+            always_build: false,
+            background_check: false,
+            max_dependencies: None,
+            manifest_wrap_width: None,
+            skip_invalid_deps: false,
+            max_dependencies_severity: Severity::default(),
+            default_dependencies: Vec::new(),
+            templates: Default::default(),
+            scaffolds: Default::default(),
+            lints: Default::default(),
+            extra_manifest: None,
             subprocesses: Default::default(),
         })
     }
 
-    pub fn get_or_create() -> Result<Self> {
+    pub fn file_path() -> Result<PathBuf> {
         #[cfg(unix)]
         let config_file_path = {
             let config_dir = xdg::BaseDirectories::with_prefix("cargo-temp")?;
-            config_dir.place_config_file("config.toml")?
+            config_dir.get_config_file("config.toml")
         };
         #[cfg(windows)]
         let config_file_path = {
             let config_dir = dirs::config_dir()
                 .context("could not get config directory")?
                 .join(env!("CARGO_PKG_NAME"));
-            let _ = fs::create_dir_all(&config_dir);
 
             config_dir.join("config.toml")
         };
 
-        let config: Self = match fs::read_to_string(&config_file_path) {
-            Ok(file) => toml::de::from_str(&file)?,
-            Err(_) => {
+        Ok(config_file_path)
+    }
+
+    pub fn get_or_create() -> Result<Self> {
+        let config_file_path = Self::file_path()?;
+
+        let config: Self = match Self::read(&config_file_path)? {
+            Some(config) => config,
+            None => {
                 let config = Self::new()?;
+                if let Some(parent) = config_file_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
                 fs::write(&config_file_path, toml::ser::to_string(&config)?)?;
                 log::info!("Config file created at: {}", config_file_path.display());
 
@@ -83,6 +211,107 @@ impl Config {
 
         Ok(config)
     }
+
+    /// Read the config file without creating it, the default config is used if it's missing.
+    pub fn get() -> Result<Self> {
+        match Self::read(&Self::file_path()?)? {
+            Some(config) => Ok(config),
+            None => Self::new(),
+        }
+    }
+
+    fn read(config_file_path: &Path) -> Result<Option<Self>> {
+        let Ok(file) = fs::read_to_string(config_file_path) else {
+            return Ok(None);
+        };
+
+        let mut config: Self = toml::de::from_str(&file)?;
+        config.expand_paths()?;
+
+        Ok(Some(config))
+    }
+
+    /// Expand `~` and the environment variables in the paths of the config file.
+    fn expand_paths(&mut self) -> Result<()> {
+        self.temporary_project_dir =
+            expand_path(&self.temporary_project_dir).context("invalid `temporary_project_dir`")?;
+
+        for (path, setting) in [
+            (&mut self.preserved_project_dir, "preserved_project_dir"),
+            (&mut self.cargo_target_dir, "cargo_target_dir"),
+        ] {
+            if let Some(path) = path {
+                *path = expand_path(path).with_context(|| format!("invalid `{setting}`"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the user's home directory from `HOME` (or `USERPROFILE` on Windows).
+pub fn home_dir() -> Option<PathBuf> {
+    #[cfg(unix)]
+    let home = env::var_os("HOME");
+
+    #[cfg(windows)]
+    let home = env::var_os("USERPROFILE");
+
+    home.filter(|x| !x.is_empty()).map(PathBuf::from)
+}
+
+/// Replace a leading `~` by the home directory and `$VAR` or `${VAR}` by the value of the
+/// environment variable.
+fn expand_path(path: &Path) -> Result<PathBuf> {
+    static RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"\$(\{(?P<braced>\w+)\}|(?P<name>\w+))")
+            .expect("environment variable's regex must be compiled")
+    });
+
+    // A path that is not UTF-8 can't contain something to expand written in the config file.
+    let Some(s) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+
+    let mut expanded = String::with_capacity(s.len());
+    let mut last = 0;
+    for caps in RE.captures_iter(s) {
+        let var = caps.get(0).unwrap();
+        let name = caps
+            .name("braced")
+            .or_else(|| caps.name("name"))
+            .unwrap()
+            .as_str();
+        let value =
+            env::var(name).with_context(|| format!("environment variable `{name}` is not set"))?;
+
+        expanded.push_str(&s[last..var.start()]);
+        expanded.push_str(&value);
+        last = var.end();
+    }
+    expanded.push_str(&s[last..]);
+
+    let rest = match expanded.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', std::path::MAIN_SEPARATOR]) => {
+            rest.trim_start_matches(['/', std::path::MAIN_SEPARATOR])
+        }
+        _ => return Ok(PathBuf::from(expanded)),
+    };
+
+    let home = home_dir().context("cannot find the home directory")?;
+    if rest.is_empty() {
+        Ok(home)
+    } else {
+        Ok(home.join(rest))
+    }
+}
+
+/// File created by a scaffold, its path is relative to the project's directory.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ScaffoldFile {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub content: String,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -91,3 +320,99 @@ pub enum Depth {
     Active(bool),
     Level(u8),
 }
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warn,
+    #[default]
+    Error,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFileMode {
+    #[default]
+    Append,
+    Rotate,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DedupStrategy {
+    #[default]
+    CliWins,
+    MergeFeatures,
+    Error,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subprocess_env() {
+        let config: Config = toml::de::from_str(
+            r#"
+            temporary_project_dir = "/tmp/cargo-temp"
+
+            [[subprocess]]
+            command = "cargo watch -x run"
+            foreground = false
+
+            [subprocess.env]
+            RUST_LOG = "debug"
+            "#,
+        )
+        .unwrap();
+
+        let env = BTreeMap::from([("RUST_LOG".to_string(), "debug".to_string())]);
+        assert_eq!(config.subprocesses[0].env.as_ref(), Some(&env));
+
+        let config: Config = toml::de::from_str(&toml::ser::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config.subprocesses[0].env.as_ref(), Some(&env));
+    }
+
+    #[test]
+    fn expand_paths() {
+        let home = home_dir().unwrap();
+
+        assert_eq!(expand_path(Path::new("~")).unwrap(), home);
+        assert_eq!(expand_path(Path::new("~/sub")).unwrap(), home.join("sub"));
+        #[cfg(unix)]
+        for path in ["$HOME/sub", "${HOME}/sub"] {
+            assert_eq!(expand_path(Path::new(path)).unwrap(), home.join("sub"));
+        }
+        assert_eq!(
+            expand_path(Path::new("/tmp/~user")).unwrap(),
+            PathBuf::from("/tmp/~user")
+        );
+        assert_eq!(
+            expand_path(Path::new("~user/sub")).unwrap(),
+            PathBuf::from("~user/sub")
+        );
+        assert!(expand_path(Path::new("$CARGO_TEMP_UNSET_VARIABLE/sub")).is_err());
+    }
+
+    #[test]
+    fn scaffolds() {
+        let config: Config = toml::de::from_str(
+            r#"
+            temporary_project_dir = "/tmp/cargo-temp"
+
+            [[scaffolds.web]]
+            path = "src/routes.rs"
+            content = "pub fn routes() {}\n"
+
+            [[scaffolds.web]]
+            path = "static/.gitkeep"
+            "#,
+        )
+        .unwrap();
+
+        let web = &config.scaffolds["web"];
+        assert_eq!(web[0].path, PathBuf::from("src/routes.rs"));
+        assert_eq!(web[0].content, "pub fn routes() {}\n");
+        assert_eq!(web[1].content, "");
+    }
+}
@@ -1,26 +1,125 @@
-use anyhow::{bail, Result};
+use crate::config::{home_dir, DedupStrategy};
+use anyhow::{bail, ensure, Context, Result};
 use regex::Regex;
-use std::sync::LazyLock;
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Dependency {
     CratesIo {
         name: String,
+        rename: Option<String>,
         version: Option<String>,
         features: Vec<String>,
+        default_features: bool,
+        kind: DependencyKind,
     },
     Repository {
-        branch: Option<String>,
+        git_ref: Option<GitRef>,
         name: String,
-        rev: Option<String>,
+        rename: Option<String>,
         url: String,
         features: Vec<String>,
+        default_features: bool,
+        kind: DependencyKind,
     },
+    Path {
+        name: String,
+        rename: Option<String>,
+        path: PathBuf,
+        features: Vec<String>,
+        default_features: bool,
+        kind: DependencyKind,
+    },
+}
+
+impl Dependency {
+    pub fn name(&self) -> &str {
+        match self {
+            Dependency::CratesIo { name, .. }
+            | Dependency::Repository { name, .. }
+            | Dependency::Path { name, .. } => name,
+        }
+    }
+
+    pub fn rename(&self) -> Option<&str> {
+        match self {
+            Dependency::CratesIo { rename, .. }
+            | Dependency::Repository { rename, .. }
+            | Dependency::Path { rename, .. } => rename.as_deref(),
+        }
+    }
+
+    /// Name of the dependency in `Cargo.toml` and in the code, the crate's name unless it's
+    /// renamed.
+    pub fn key(&self) -> &str {
+        self.rename().unwrap_or(self.name())
+    }
+
+    pub fn kind(&self) -> DependencyKind {
+        match self {
+            Dependency::CratesIo { kind, .. }
+            | Dependency::Repository { kind, .. }
+            | Dependency::Path { kind, .. } => *kind,
+        }
+    }
+}
+
+/// Branch, tag or revision of a repository, cargo accepts only one of them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GitRef {
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+impl GitRef {
+    /// Name of the `Cargo.toml` key and of the `cargo add` option of this reference.
+    pub fn key(&self) -> &'static str {
+        match self {
+            GitRef::Branch(_) => "branch",
+            GitRef::Tag(_) => "tag",
+            GitRef::Rev(_) => "rev",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            GitRef::Branch(value) | GitRef::Tag(value) | GitRef::Rev(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum DependencyKind {
+    #[default]
+    Normal,
+    Dev,
+    Build,
+}
+
+impl DependencyKind {
+    /// Name of the `Cargo.toml` table where this kind of dependency is written.
+    pub fn table(&self) -> &'static str {
+        match self {
+            DependencyKind::Normal => "dependencies",
+            DependencyKind::Dev => "dev-dependencies",
+            DependencyKind::Build => "build-dependencies",
+        }
+    }
 }
 
+/// Prefixes that are not a renaming of the dependency.
+const RESERVED_PREFIXES: &[&str] = &["dev", "build", "path"];
+
 pub fn parse_dependency(s: &str) -> Result<Dependency> {
+    let (s, rename) = split_rename(s)?;
+    let s = s.as_str();
+
     static RE: LazyLock<Regex> = LazyLock::new(|| {
-        Regex::new(r"^((?P<name>[^+=/]+)=)?(?P<version>((?P<url>\w+://([^:@]+(:[^@]+)?@)?[^#+]*?(?P<url_end>/[^#+/]+)?)(#branch=(?P<branch>[^+]+)|#rev=(?P<rev>[^+]+))?)|[^+]+)?(?P<features>(\+[^+]+)*)$")
+        Regex::new(r"^((?P<kind_prefix>dev|build):)?((?P<name>[^+=@/%!]+)[=@])?(?P<version>((?P<url>\w+://([^:@]+(:[^@]+)?@)?[^#+%!]*?(?P<url_end>/[^#+/%!]+)?)(?P<git_ref>#[^+%!]*)?)|[^+%!]+)?(?P<default>!!?default)?(?P<features>(\+\+[^+%]+)?(\+[^+%]+)*)(%(?P<kind>dev|build))?$")
             .expect("dependency's regex must be compiled")
     });
 
@@ -31,14 +130,60 @@ pub fn parse_dependency(s: &str) -> Result<Dependency> {
                 .map(|x| {
                     x.as_str()
                         .split('+')
-                        .map(|x| x.to_string())
                         .skip(1)
+                        .flat_map(|x| x.split(','))
+                        .filter(|x| !x.is_empty())
+                        .map(|x| x.to_string())
                         .collect::<Vec<String>>()
                 })
                 .unwrap();
+            // `!default` and `++feature` disable the default features, `!!default` enables them.
+            let default_features = match (
+                caps.name("default").map(|x| x.as_str()),
+                caps.name("features").unwrap().as_str().starts_with("++"),
+            ) {
+                (Some("!!default"), true) => {
+                    bail!("default features can't be both enabled and disabled")
+                }
+                (Some("!default"), _) | (_, true) => false,
+                _ => true,
+            };
             let name: Option<String> = caps.name("name").map(|x| x.as_str().to_string());
+            let kind = match (
+                caps.name("kind_prefix").map(|x| x.as_str()),
+                caps.name("kind").map(|x| x.as_str()),
+            ) {
+                (Some(prefix), Some(suffix)) if prefix != suffix => {
+                    bail!("the dependency can't be both a {prefix} and a {suffix} dependency")
+                }
+                (Some("dev"), _) | (_, Some("dev")) => DependencyKind::Dev,
+                (Some("build"), _) | (_, Some("build")) => DependencyKind::Build,
+                _ => DependencyKind::Normal,
+            };
 
             if let Some(url) = caps.name("url").map(|x| x.as_str().to_string()) {
+                if let Some((crate_name, version)) = parse_crate_page_url(&url) {
+                    if let Some(name) = name.filter(|x| *x != crate_name) {
+                        bail!(
+                            "the name `{name}` doesn't match the crate `{crate_name}` of the URL"
+                        );
+                    }
+
+                    ensure!(
+                        caps.name("git_ref").is_none(),
+                        "a crate of crates.io has no branch, tag or revision"
+                    );
+
+                    return Ok(Dependency::CratesIo {
+                        name: crate_name,
+                        rename,
+                        version,
+                        features,
+                        default_features,
+                        kind,
+                    });
+                }
+
                 let name = if let Some(name) = name {
                     name
                 } else if let Some(url_end) = caps.name("url_end").map(|x| x.as_str()) {
@@ -50,25 +195,72 @@ pub fn parse_dependency(s: &str) -> Result<Dependency> {
                     bail!("could not guess name of crate in URL");
                 };
 
+                let git_ref = caps
+                    .name("git_ref")
+                    .map(|x| parse_git_ref(x.as_str()))
+                    .transpose()?;
+
                 Ok(Dependency::Repository {
-                    branch: caps.name("branch").map(|x| x.as_str().to_string()),
-                    rev: caps.name("rev").map(|x| x.as_str().to_string()),
+                    git_ref,
                     features,
+                    default_features,
                     url,
                     name,
+                    rename,
+                    kind,
                 })
             } else if let Some(name) = name {
+                let version = caps.name("version").map(|x| x.as_str().to_string());
+
+                if let Some(path) = version.as_deref().map(parse_path).transpose()?.flatten() {
+                    return Ok(Dependency::Path {
+                        name,
+                        rename,
+                        path,
+                        features,
+                        default_features,
+                        kind,
+                    });
+                }
+
                 Ok(Dependency::CratesIo {
                     name,
-                    version: caps.name("version").map(|x| x.as_str().to_string()),
+                    rename,
+                    version,
                     features,
+                    default_features,
+                    kind,
                 })
             } else {
-                let end = caps.name("features").unwrap().start();
+                let start = caps.name("kind_prefix").map_or(0, |x| x.end() + 1);
+                let end = caps
+                    .name("default")
+                    .unwrap_or(caps.name("features").unwrap())
+                    .start();
+                let name = &s[start..end];
+
+                if let Some(path) = parse_path(name)? {
+                    let name = crate_name(&path)
+                        .or_else(|| Some(path.file_name()?.to_string_lossy().to_string()))
+                        .context("could not guess name of crate in path")?;
+
+                    return Ok(Dependency::Path {
+                        name,
+                        rename,
+                        path,
+                        features,
+                        default_features,
+                        kind,
+                    });
+                }
+
                 Ok(Dependency::CratesIo {
-                    name: s[..end].to_string(),
+                    name: name.to_string(),
+                    rename,
                     version: None,
                     features,
+                    default_features,
+                    kind,
                 })
             }
         }
@@ -76,48 +268,665 @@ pub fn parse_dependency(s: &str) -> Result<Dependency> {
     }
 }
 
-pub fn format_dependency(dependency: &Dependency) -> String {
+/// Removes the `<rename>:` prefix of a dependency, after the kind's prefix, like in
+/// `json:serde_json=1` or `dev:json:serde_json`.
+///
+/// The scheme of a URL like `https://`, a Windows drive like `C:\` or `C:/` and the reserved
+/// prefixes are not a renaming. The renaming must be followed by a crate name, a URL or a path.
+fn split_rename(s: &str) -> Result<(String, Option<String>)> {
+    static RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?P<kind_prefix>(dev|build):)?(?P<rename>[A-Za-z_][\w-]*):(?P<rest>.*)$")
+            .expect("rename's regex must be compiled")
+    });
+    static SOURCE_RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(\w+://|path:|\.{0,2}/|~/|[A-Za-z_][\w-]*([=@+!%]|$))")
+            .expect("renamed dependency's regex must be compiled")
+    });
+
+    let is_rename = |rename: &str, rest: &str| {
+        let is_drive = rename.len() == 1 && rest.starts_with(['\\', '/']);
+
+        !RESERVED_PREFIXES.contains(&rename) && !rest.starts_with("//") && !is_drive
+    };
+
+    match RE.captures(s) {
+        Some(caps) if is_rename(&caps["rename"], &caps["rest"]) => {
+            let rest = &caps["rest"];
+            ensure!(
+                SOURCE_RE.is_match(rest) || cfg!(windows) && Path::new(rest).is_absolute(),
+                "invalid dependency `{s}`, `{}:` must be followed by a crate name, a URL or a path",
+                &caps["rename"]
+            );
+
+            Ok((
+                format!(
+                    "{}{rest}",
+                    caps.name("kind_prefix").map_or("", |x| x.as_str()),
+                ),
+                Some(caps["rename"].to_string()),
+            ))
+        }
+        _ => Ok((s.to_string(), None)),
+    }
+}
+
+/// Returns the name and the version of the crate of a crates.io or lib.rs page, like
+/// `https://crates.io/crates/serde/1.0.200`.
+fn parse_crate_page_url(url: &str) -> Option<(String, Option<String>)> {
+    static RE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^https?://(www\.)?(crates\.io|lib\.rs)/crates/(?P<name>[\w-]+)(/(?P<version>[^/]+))?/?$")
+            .expect("crate page's regex must be compiled")
+    });
+
+    let caps = RE.captures(url)?;
+
+    Some((
+        caps["name"].to_string(),
+        caps.name("version").map(|x| x.as_str().to_string()),
+    ))
+}
+
+/// Returns the branch, the tag or the revision of a repository from `#branch=<name>`,
+/// `#tag=<name>` or `#rev=<sha>`.
+///
+/// A bare `#<ref>` is a revision if it's a hexadecimal string of at least 7 characters, a branch
+/// otherwise.
+fn parse_git_ref(s: &str) -> Result<GitRef> {
+    let refs = s.split('#').skip(1).collect::<Vec<_>>();
+
+    ensure!(
+        refs.len() == 1,
+        "only one of branch, tag or rev can be specified"
+    );
+
+    let (key, value) = match refs[0].split_once('=') {
+        Some((key, value)) => (key, value),
+        None if refs[0].len() >= 7 && refs[0].bytes().all(|x| x.is_ascii_hexdigit()) => {
+            ("rev", refs[0])
+        }
+        None => ("branch", refs[0]),
+    };
+
+    ensure!(!value.is_empty(), "the {key} of the repository is empty");
+
+    let value = value.to_string();
+    match key {
+        "branch" => Ok(GitRef::Branch(value)),
+        "tag" => Ok(GitRef::Tag(value)),
+        "rev" => Ok(GitRef::Rev(value)),
+        _ => bail!("unknown git reference `{key}`, expected `branch`, `tag` or `rev`"),
+    }
+}
+
+/// Parse the dependencies of the arguments and report all the invalid ones at once.
+///
+/// With `skip_invalid`, the invalid dependencies are skipped with a warning instead.
+pub fn parse_dependencies(args: &[String], skip_invalid: bool) -> Result<Vec<Dependency>> {
+    let mut dependencies = Vec::with_capacity(args.len());
+    let mut errors = Vec::new();
+
+    for arg in args {
+        match parse_dependency(arg) {
+            Ok(dependency) => dependencies.push(dependency),
+            Err(err) => errors.push(format!("`{arg}`: {err}")),
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(dependencies);
+    }
+
+    if skip_invalid {
+        for error in errors {
+            log::warn!("skipping invalid dependency {error}");
+        }
+
+        Ok(dependencies)
+    } else {
+        bail!("invalid dependencies:\n  {}", errors.join("\n  "))
+    }
+}
+
+/// Returns the absolute path of a path dependency, written with the `path:` prefix, starting
+/// with `/`, `./`, `../` or `~/`, or an absolute Windows path like `C:\deps\foo`.
+///
+/// A Rust file is not a path dependency, it is used as `src/main.rs`.
+fn parse_path(s: &str) -> Result<Option<PathBuf>> {
+    let (path, explicit) = match s.strip_prefix("path:") {
+        Some(path) => (path, true),
+        None if ["/", "./", "../", "~/"].iter().any(|x| s.starts_with(x))
+            || cfg!(windows) && Path::new(s).is_absolute() =>
+        {
+            (s, false)
+        }
+        None => return Ok(None),
+    };
+
+    let path = match path.strip_prefix("~/") {
+        Some(path) => home_dir()
+            .context("cannot find the home directory")?
+            .join(path),
+        None => PathBuf::from(path),
+    };
+
+    if !explicit && path.extension().is_some_and(|x| x == "rs") {
+        return Ok(None);
+    }
+
+    // The temporary project is somewhere else, the path must not be relative.
+    let path = path
+        .canonicalize()
+        .with_context(|| format!("cannot find path dependency `{}`", path.display()))?;
+
+    if !path.is_dir() {
+        bail!("path dependency `{}` is not a directory", path.display());
+    }
+
+    Ok(Some(path))
+}
+
+/// Returns the name of the project's package, if any.
+pub fn crate_name(project_path: &Path) -> Option<String> {
+    std::fs::read_to_string(project_path.join("Cargo.toml"))
+        .ok()?
+        .parse::<toml::Table>()
+        .ok()?
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+/// Format the dependency as a line of `Cargo.toml`, its features are written on multiple lines if
+/// the line would be longer than `wrap_width`.
+pub fn format_dependency(dependency: &Dependency, wrap_width: usize) -> String {
     match dependency {
         Dependency::CratesIo {
             name,
+            rename,
             version,
             features,
+            default_features,
+            ..
         } => {
-            if let Some(version) = version {
-                if !features.is_empty() {
-                    format!("{name} = {{ version = \"{version}\", features = {features:?} }}",)
-                } else {
-                    format!("{name} = \"{version}\"")
-                }
-            } else if !features.is_empty() {
-                format!("{name} = {{ version = \"*\", features = {features:?} }}")
+            let version = version.as_deref().unwrap_or("*");
+
+            if features.is_empty() && *default_features && rename.is_none() {
+                format!("{name} = \"{version}\"")
             } else {
-                format!("{name} = \"*\"")
+                let mut string = open_inline_table(name, rename.as_deref());
+                string.push_str(&format!("version = \"{version}\""));
+
+                if !default_features {
+                    string.push_str(", default-features = false")
+                }
+
+                close_inline_table(string, features, wrap_width)
             }
         }
         Dependency::Repository {
             name,
+            rename,
             url,
-            branch,
-            rev,
+            git_ref,
+            features,
+            default_features,
+            ..
+        } => {
+            let mut string = open_inline_table(name, rename.as_deref());
+            string.push_str(&format!("git = {url:?}"));
+
+            if let Some(git_ref) = git_ref {
+                string.push_str(&format!(", {} = {:?}", git_ref.key(), git_ref.value()))
+            }
+            if !default_features {
+                string.push_str(", default-features = false")
+            }
+
+            close_inline_table(string, features, wrap_width)
+        }
+        Dependency::Path {
+            name,
+            rename,
+            path,
             features,
+            default_features,
+            ..
+        } => {
+            let mut string = open_inline_table(name, rename.as_deref());
+            string.push_str(&format!("path = {:?}", path.to_string_lossy()));
+
+            if !default_features {
+                string.push_str(", default-features = false")
+            }
+
+            close_inline_table(string, features, wrap_width)
+        }
+    }
+}
+
+/// Default maximum length of a dependency line before its features are written on multiple lines.
+pub const DEFAULT_WRAP_WIDTH: usize = 100;
+
+/// Start the inline table of a dependency, with the crate's name as `package` if it's renamed.
+fn open_inline_table(name: &str, rename: Option<&str>) -> String {
+    match rename {
+        Some(rename) => format!("{rename} = {{ package = {name:?}, "),
+        None => format!("{name} = {{ "),
+    }
+}
+
+/// Add the features to the inline table of a dependency and close it.
+fn close_inline_table(mut string: String, features: &[String], wrap_width: usize) -> String {
+    if features.is_empty() {
+        string.push_str(" }");
+        return string;
+    }
+
+    let inline = format!("{string}, features = {features:?} }}");
+    if inline.chars().count() <= wrap_width {
+        return inline;
+    }
+
+    string.push_str(", features = [\n");
+    for feature in features {
+        string.push_str(&format!("    {feature:?},\n"));
+    }
+    string.push_str("] }");
+
+    string
+}
+
+/// Returns the arguments of `cargo add` adding this dependency.
+pub fn cargo_add_args(dependency: &Dependency) -> Vec<String> {
+    let mut args = Vec::new();
+
+    match dependency {
+        Dependency::CratesIo { name, version, .. } => match version {
+            Some(version) => args.push(format!("{name}@{version}")),
+            None => args.push(name.to_string()),
+        },
+        Dependency::Repository {
+            name, url, git_ref, ..
         } => {
-            let mut string = format!("{name} = {{ git = {url:?}");
+            args.extend([name.to_string(), "--git".to_string(), url.to_string()]);
 
-            if let Some(branch) = branch {
-                string.push_str(format!(", branch = {branch:?}").as_str())
+            if let Some(git_ref) = git_ref {
+                args.extend([format!("--{}", git_ref.key()), git_ref.value().to_string()]);
             }
-            if let Some(rev) = rev {
-                string.push_str(format!(", rev = {rev:?}").as_str())
+        }
+        Dependency::Path { path, .. } => {
+            args.extend(["--path".to_string(), path.to_string_lossy().to_string()]);
+        }
+    }
+
+    let (Dependency::CratesIo {
+        features,
+        default_features,
+        kind,
+        ..
+    }
+    | Dependency::Repository {
+        features,
+        default_features,
+        kind,
+        ..
+    }
+    | Dependency::Path {
+        features,
+        default_features,
+        kind,
+        ..
+    }) = dependency;
+
+    if let Some(rename) = dependency.rename() {
+        args.extend(["--rename".to_string(), rename.to_string()]);
+    }
+    if !default_features {
+        args.push("--no-default-features".to_string());
+    }
+    if !features.is_empty() {
+        args.extend(["--features".to_string(), features.join(",")]);
+    }
+    match kind {
+        DependencyKind::Normal => {}
+        DependencyKind::Dev => args.push("--dev".to_string()),
+        DependencyKind::Build => args.push("--build".to_string()),
+    }
+
+    args
+}
+
+/// Merge the dependencies with the same name in `Cargo.toml` and kind.
+///
+/// The dependencies must be ordered from the lowest to the highest priority,
+/// the command line's dependencies being the last ones.
+pub fn dedup_dependencies(
+    dependencies: Vec<Dependency>,
+    strategy: DedupStrategy,
+) -> Result<Vec<Dependency>> {
+    let mut deduped: Vec<Dependency> = Vec::with_capacity(dependencies.len());
+
+    for dependency in dependencies {
+        let Some(existing) = deduped
+            .iter_mut()
+            .find(|x| x.key() == dependency.key() && x.kind() == dependency.kind())
+        else {
+            deduped.push(dependency);
+            continue;
+        };
+
+        match strategy {
+            DedupStrategy::Error => {
+                bail!(
+                    "dependency `{}` is specified more than once",
+                    dependency.key()
+                )
             }
-            if !features.is_empty() {
-                string.push_str(format!(", features = {features:?}").as_str())
+            DedupStrategy::MergeFeatures => merge_features(existing, dependency)?,
+            DedupStrategy::CliWins => *existing = dependency,
+        }
+    }
+
+    Ok(deduped)
+}
+
+/// Merge the features of two occurrences of a dependency.
+///
+/// They must have the same source: the same crate and the same version when both have one, the
+/// same repository and reference or the same path.
+fn merge_features(existing: &mut Dependency, other: Dependency) -> Result<()> {
+    let key = other.key().to_string();
+
+    let (features, default_features, other_features, other_default_features) =
+        match (existing, other) {
+            (
+                Dependency::CratesIo {
+                    name,
+                    version,
+                    features,
+                    default_features,
+                    ..
+                },
+                Dependency::CratesIo {
+                    name: other_name,
+                    version: other_version,
+                    features: other_features,
+                    default_features: other_default_features,
+                    ..
+                },
+            ) if *name == other_name => {
+                if let Some(other_version) = other_version {
+                    match version.as_deref() {
+                        Some(version) => ensure!(
+                            version == other_version,
+                            "dependency `{key}` is specified with the versions `{version}` and \
+                            `{other_version}`"
+                        ),
+                        None => *version = Some(other_version),
+                    }
+                }
+
+                (
+                    features,
+                    default_features,
+                    other_features,
+                    other_default_features,
+                )
             }
+            (
+                Dependency::Repository {
+                    name,
+                    url,
+                    git_ref,
+                    features,
+                    default_features,
+                    ..
+                },
+                Dependency::Repository {
+                    name: other_name,
+                    url: other_url,
+                    git_ref: other_git_ref,
+                    features: other_features,
+                    default_features: other_default_features,
+                    ..
+                },
+            ) if *name == other_name && *url == other_url && *git_ref == other_git_ref => (
+                features,
+                default_features,
+                other_features,
+                other_default_features,
+            ),
+            (
+                Dependency::Path {
+                    name,
+                    path,
+                    features,
+                    default_features,
+                    ..
+                },
+                Dependency::Path {
+                    name: other_name,
+                    path: other_path,
+                    features: other_features,
+                    default_features: other_default_features,
+                    ..
+                },
+            ) if *name == other_name && *path == other_path => (
+                features,
+                default_features,
+                other_features,
+                other_default_features,
+            ),
+            _ => bail!("dependency `{key}` is specified more than once with different sources"),
+        };
+
+    for feature in other_features {
+        if !features.contains(&feature) {
+            features.push(feature);
+        }
+    }
 
-            string.push_str(" }");
+    *default_features |= other_default_features;
 
-            string
+    Ok(())
+}
+
+/// Count the dependencies of a `Cargo.toml`, including the dev and build dependencies.
+pub fn count_manifest_dependencies(manifest: &str) -> Result<usize> {
+    let manifest: toml::Table = toml::from_str(manifest).context("cannot parse manifest")?;
+
+    [
+        DependencyKind::Normal,
+        DependencyKind::Dev,
+        DependencyKind::Build,
+    ]
+    .iter()
+    .filter_map(|kind| manifest.get(kind.table()).map(|table| (kind, table)))
+    .map(|(kind, table)| {
+        table
+            .as_table()
+            .map(|table| table.len())
+            .with_context(|| format!("`{}` is not a table", kind.table()))
+    })
+    .sum()
+}
+
+/// Parse a dependencies file, either a `Cargo.toml` snippet or a list of dependencies.
+///
+/// The file is read as a snippet if it is valid TOML with a dependency table. Otherwise, each
+/// line is a dependency using the syntax of the arguments. Empty lines and lines starting with
+/// `#` are ignored.
+pub fn parse_dependencies_file(content: &str) -> Result<Vec<Dependency>> {
+    let is_manifest = content.parse::<toml::Table>().is_ok_and(|manifest| {
+        [
+            DependencyKind::Normal,
+            DependencyKind::Dev,
+            DependencyKind::Build,
+        ]
+        .iter()
+        .any(|kind| manifest.contains_key(kind.table()))
+    });
+
+    if is_manifest {
+        return parse_manifest_dependencies(content);
+    }
+
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| parse_dependency(line).with_context(|| format!("invalid dependency `{line}`")))
+        .collect()
+}
+
+/// Parse the dependency tables of a `Cargo.toml` snippet.
+///
+/// If the snippet doesn't contain any dependency table, its top-level keys are
+/// read as `[dependencies]`.
+pub fn parse_manifest_dependencies(manifest: &str) -> Result<Vec<Dependency>> {
+    let manifest: toml::Table = toml::from_str(manifest).context("cannot parse manifest")?;
+    let kinds = [
+        DependencyKind::Normal,
+        DependencyKind::Dev,
+        DependencyKind::Build,
+    ];
+
+    let tables = if kinds.iter().any(|x| manifest.contains_key(x.table())) {
+        kinds
+            .iter()
+            .filter_map(|kind| manifest.get(kind.table()).map(|table| (*kind, table)))
+            .map(|(kind, table)| {
+                table
+                    .as_table()
+                    .with_context(|| format!("`{}` is not a table", kind.table()))
+                    .map(|table| (kind, table))
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        vec![(DependencyKind::Normal, &manifest)]
+    };
+
+    tables
+        .into_iter()
+        .flat_map(|(kind, table)| {
+            table
+                .iter()
+                .map(move |(name, value)| parse_manifest_dependency(name, value, kind))
+        })
+        .collect()
+}
+
+fn parse_manifest_dependency(
+    name: &str,
+    value: &toml::Value,
+    kind: DependencyKind,
+) -> Result<Dependency> {
+    let name = name.to_string();
+
+    let table = match value {
+        toml::Value::String(version) => {
+            return Ok(Dependency::CratesIo {
+                name,
+                rename: None,
+                version: Some(version.to_string()),
+                features: Vec::new(),
+                default_features: true,
+                kind,
+            })
         }
+        toml::Value::Table(table) => table,
+        _ => bail!("invalid dependency `{name}`"),
+    };
+
+    let get_string = |key: &str| -> Result<Option<String>> {
+        table
+            .get(key)
+            .map(|x| {
+                x.as_str()
+                    .map(|x| x.to_string())
+                    .with_context(|| format!("`{key}` of `{name}` is not a string"))
+            })
+            .transpose()
+    };
+
+    if table.contains_key("workspace") {
+        bail!("`workspace` is not supported in dependency `{name}`");
+    }
+
+    // The key of a dependency with `package` is a renaming of the crate.
+    let (name, rename) = match get_string("package")? {
+        Some(package) => (package, Some(name.clone())),
+        None => (name.clone(), None),
+    };
+
+    let features = table
+        .get("features")
+        .map(|x| {
+            x.as_array()
+                .and_then(|x| {
+                    x.iter()
+                        .map(|x| x.as_str().map(|x| x.to_string()))
+                        .collect::<Option<Vec<String>>>()
+                })
+                .with_context(|| format!("`features` of `{name}` is not an array of strings"))
+        })
+        .transpose()?
+        .unwrap_or_default();
+    let default_features = table
+        .get("default-features")
+        .or_else(|| table.get("default_features"))
+        .map(|x| {
+            x.as_bool()
+                .with_context(|| format!("`default-features` of `{name}` is not a boolean"))
+        })
+        .transpose()?
+        .unwrap_or(true);
+
+    // Like in the arguments, a relative path is relative to the current directory.
+    if let Some(path) = get_string("path")? {
+        let path = parse_path(&format!("path:{path}"))?.context("invalid path dependency")?;
+
+        Ok(Dependency::Path {
+            path,
+            features,
+            default_features,
+            name,
+            rename,
+            kind,
+        })
+    } else if let Some(url) = get_string("git")? {
+        let git_refs = [
+            get_string("branch")?.map(GitRef::Branch),
+            get_string("tag")?.map(GitRef::Tag),
+            get_string("rev")?.map(GitRef::Rev),
+        ];
+        let mut git_refs = git_refs.into_iter().flatten();
+        let git_ref = git_refs.next();
+
+        ensure!(
+            git_refs.next().is_none(),
+            "only one of `branch`, `tag` or `rev` can be specified in dependency `{name}`"
+        );
+
+        Ok(Dependency::Repository {
+            git_ref,
+            features,
+            default_features,
+            url,
+            name,
+            rename,
+            kind,
+        })
+    } else {
+        Ok(Dependency::CratesIo {
+            version: get_string("version")?,
+            features,
+            default_features,
+            name,
+            rename,
+            kind,
+        })
     }
 }
 
@@ -137,7 +946,7 @@ mod dependency_tests {
                     "cannot parse dependency"
                 );
                 assert_eq!(
-                    format_dependency(&dependency),
+                    format_dependency(&dependency, DEFAULT_WRAP_WIDTH),
                     $out,
                     "cannot format dependency"
                 );
@@ -159,7 +968,7 @@ mod dependency_tests {
                     "cannot parse dependency without package name"
                 );
                 assert_eq!(
-                    format_dependency(&dependency),
+                    format_dependency(&dependency, DEFAULT_WRAP_WIDTH),
                     $out,
                     "cannot format dependency"
                 );
@@ -171,8 +980,11 @@ mod dependency_tests {
         dependency,
         Dependency::CratesIo {
             name: "anyhow".to_string(),
+            rename: None,
             version: None,
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "anyhow",
         "anyhow = \"*\""
@@ -182,8 +994,11 @@ mod dependency_tests {
         dependency_with_version,
         Dependency::CratesIo {
             name: "anyhow".to_string(),
+            rename: None,
             version: Some("0.1".to_string()),
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "anyhow=0.1",
         "anyhow = \"0.1\""
@@ -193,8 +1008,11 @@ mod dependency_tests {
         dependency_with_exact_version,
         Dependency::CratesIo {
             name: "anyhow".to_string(),
+            rename: None,
             version: Some("=0.1".to_string()),
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "anyhow==0.1",
         "anyhow = \"=0.1\""
@@ -204,19 +1022,53 @@ mod dependency_tests {
         dependency_with_maximal_version,
         Dependency::CratesIo {
             name: "anyhow".to_string(),
+            rename: None,
             version: Some("<1.0.2".to_string()),
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "anyhow=<1.0.2",
         "anyhow = \"<1.0.2\""
     );
 
+    test_dependency!(
+        dependency_with_caret_version,
+        Dependency::CratesIo {
+            name: "serde".to_string(),
+            rename: None,
+            version: Some("^1.2".to_string()),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "serde=^1.2",
+        "serde = \"^1.2\""
+    );
+
+    test_dependency!(
+        dependency_with_caret_version_and_feature,
+        Dependency::CratesIo {
+            name: "serde".to_string(),
+            rename: None,
+            version: Some("^1.2".to_string()),
+            features: vec!["derive".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "serde=^1.2+derive",
+        "serde = { version = \"^1.2\", features = [\"derive\"] }"
+    );
+
     test_dependency!(
         dependency_with_feature,
         Dependency::CratesIo {
             name: "tokio".to_string(),
+            rename: None,
             version: None,
             features: vec!["io_std".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio+io_std",
         "tokio = { version = \"*\", features = [\"io_std\"] }"
@@ -226,19 +1078,53 @@ mod dependency_tests {
         dependency_with_features,
         Dependency::CratesIo {
             name: "tokio".to_string(),
+            rename: None,
             version: None,
             features: vec!["io_std".to_string(), "io_utils".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio+io_std+io_utils",
         "tokio = { version = \"*\", features = [\"io_std\", \"io_utils\"] }"
     );
 
+    test_dependency!(
+        dependency_with_comma_separated_features,
+        Dependency::CratesIo {
+            name: "tokio".to_string(),
+            rename: None,
+            version: None,
+            features: vec!["rt".to_string(), "macros".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "tokio+rt,macros",
+        "tokio = { version = \"*\", features = [\"rt\", \"macros\"] }"
+    );
+
+    test_dependency!(
+        dependency_with_mixed_features,
+        Dependency::CratesIo {
+            name: "tokio".to_string(),
+            rename: None,
+            version: Some("1.0".to_string()),
+            features: vec!["rt".to_string(), "macros".to_string(), "io_std".to_string(),],
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "tokio=1.0+rt,macros+io_std",
+        "tokio = { version = \"1.0\", features = [\"rt\", \"macros\", \"io_std\"] }"
+    );
+
     test_dependency!(
         dependency_with_version_and_feature,
         Dependency::CratesIo {
             name: "tokio".to_string(),
+            rename: None,
             version: Some("1.0".to_string()),
             features: vec!["io_std".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=1.0+io_std",
         "tokio = { version = \"1.0\", features = [\"io_std\"] }"
@@ -248,8 +1134,11 @@ mod dependency_tests {
         dependency_with_version_and_features,
         Dependency::CratesIo {
             name: "tokio".to_string(),
+            rename: None,
             version: Some("1.0".to_string()),
             features: vec!["io_std".to_string(), "io_utils".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=1.0+io_std+io_utils",
         "tokio = { version = \"1.0\", features = [\"io_std\", \"io_utils\"] }"
@@ -259,10 +1148,12 @@ mod dependency_tests {
         repository_with_http_url,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git",
         "https://github.com/tokio-rs/tokio.git",
@@ -273,10 +1164,12 @@ mod dependency_tests {
         repository_with_http_url_and_no_extension,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio",
         "https://github.com/tokio-rs/tokio",
@@ -287,10 +1180,12 @@ mod dependency_tests {
         repository_with_ssh_url,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git",
         "ssh://git@github.com/serde-rs/serde.git",
@@ -301,10 +1196,12 @@ mod dependency_tests {
         repository_with_ssh_url_and_no_extension,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde",
         "ssh://git@github.com/serde-rs/serde",
@@ -315,10 +1212,12 @@ mod dependency_tests {
         repository_with_http_url_and_feature,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: vec!["io_std".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git+io_std",
         "https://github.com/tokio-rs/tokio.git+io_std",
@@ -329,10 +1228,12 @@ mod dependency_tests {
         repository_with_ssh_url_and_feature,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: vec!["derive".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git+derive",
         "ssh://git@github.com/serde-rs/serde.git+derive",
@@ -343,10 +1244,12 @@ mod dependency_tests {
         repository_with_http_url_and_features,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: vec!["io_std".to_string(), "io_utils".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git+io_std+io_utils",
         "https://github.com/tokio-rs/tokio.git+io_std+io_utils",
@@ -357,10 +1260,12 @@ mod dependency_tests {
         repository_with_ssh_url_and_features,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: None,
-            rev: None,
+            git_ref: None,
             features: vec!["derive".to_string(), "alloc".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git+derive+alloc",
         "ssh://git@github.com/serde-rs/serde.git+derive+alloc",
@@ -371,10 +1276,12 @@ mod dependency_tests {
         repository_with_http_url_and_branch,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: Some("compat".to_string()),
-            rev: None,
+            git_ref: Some(GitRef::Branch("compat".to_string())),
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git#branch=compat",
         "https://github.com/tokio-rs/tokio.git#branch=compat",
@@ -385,10 +1292,12 @@ mod dependency_tests {
         repository_with_ssh_url_and_branch,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: Some("watt".to_string()),
-            rev: None,
+            git_ref: Some(GitRef::Branch("watt".to_string())),
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git#branch=watt",
         "ssh://git@github.com/serde-rs/serde.git#branch=watt",
@@ -399,10 +1308,12 @@ mod dependency_tests {
         repository_with_http_url_branch_and_feature,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: Some("compat".to_string()),
-            rev: None,
+            git_ref: Some(GitRef::Branch("compat".to_string())),
             features: vec!["io_std".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git#branch=compat+io_std",
         "https://github.com/tokio-rs/tokio.git#branch=compat+io_std",
@@ -413,10 +1324,12 @@ mod dependency_tests {
         repository_with_ssh_url_branch_and_feature,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: Some("watt".to_string()),
-            rev: None,
+            git_ref: Some(GitRef::Branch("watt".to_string())),
             features: vec!["derive".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git#branch=watt+derive",
         "ssh://git@github.com/serde-rs/serde.git#branch=watt+derive",
@@ -427,38 +1340,60 @@ mod dependency_tests {
         repository_with_http_url_branch_and_features,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: Some("compat".to_string()),
-            rev: None,
+            git_ref: Some(GitRef::Branch("compat".to_string())),
             features: vec!["io_std".to_string(), "io_utils".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git#branch=compat+io_std+io_utils",
         "https://github.com/tokio-rs/tokio.git#branch=compat+io_std+io_utils",
-        "tokio = { git = \"https://github.com/tokio-rs/tokio.git\", branch = \"compat\", features = [\"io_std\", \"io_utils\"] }"
+        "tokio = { git = \"https://github.com/tokio-rs/tokio.git\", branch = \"compat\", features = [\n    \"io_std\",\n    \"io_utils\",\n] }"
     );
 
     test_dependency!(
         repository_with_ssh_url_branch_and_features,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: Some("watt".to_string()),
-            rev: None,
+            git_ref: Some(GitRef::Branch("watt".to_string())),
             features: vec!["derive".to_string(), "alloc".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git#branch=watt+derive+alloc",
         "ssh://git@github.com/serde-rs/serde.git#branch=watt+derive+alloc",
-        "serde = { git = \"ssh://git@github.com/serde-rs/serde.git\", branch = \"watt\", features = [\"derive\", \"alloc\"] }"
+        "serde = { git = \"ssh://git@github.com/serde-rs/serde.git\", branch = \"watt\", features = [\n    \"derive\",\n    \"alloc\",\n] }"
+    );
+
+    test_dependency!(
+        repository_with_http_url_and_tag,
+        Dependency::Repository {
+            name: "serde".to_string(),
+            rename: None,
+            url: "https://github.com/serde-rs/serde.git".to_string(),
+            git_ref: Some(GitRef::Tag("v1.0.200".to_string())),
+            features: vec!["derive".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "serde=https://github.com/serde-rs/serde.git#tag=v1.0.200+derive",
+        "https://github.com/serde-rs/serde.git#tag=v1.0.200+derive",
+        "serde = { git = \"https://github.com/serde-rs/serde.git\", tag = \"v1.0.200\", features = [\"derive\"] }"
     );
 
     test_dependency!(
         repository_with_http_url_and_rev,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: None,
-            rev: Some("75c0777".to_string()),
+            git_ref: Some(GitRef::Rev("75c0777".to_string())),
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git#rev=75c0777",
         "https://github.com/tokio-rs/tokio.git#rev=75c0777",
@@ -469,10 +1404,12 @@ mod dependency_tests {
         repository_with_ssh_url_and_rev,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: None,
-            rev: Some("5b140361a".to_string()),
+            git_ref: Some(GitRef::Rev("5b140361a".to_string())),
             features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git#rev=5b140361a",
         "ssh://git@github.com/serde-rs/serde.git#rev=5b140361a",
@@ -483,10 +1420,12 @@ mod dependency_tests {
         repository_with_http_url_rev_and_feature,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: None,
-            rev: Some("75c0777".to_string()),
+            git_ref: Some(GitRef::Rev("75c0777".to_string())),
             features: vec!["io_std".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git#rev=75c0777+io_std",
         "https://github.com/tokio-rs/tokio.git#rev=75c0777+io_std",
@@ -497,47 +1436,881 @@ mod dependency_tests {
         repository_with_ssh_url_rev_and_feature,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: None,
-            rev: Some("5b140361a".to_string()),
+            git_ref: Some(GitRef::Rev("5b140361a".to_string())),
             features: vec!["derive".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git#rev=5b140361a+derive",
         "ssh://git@github.com/serde-rs/serde.git#rev=5b140361a+derive",
-        "serde = { git = \"ssh://git@github.com/serde-rs/serde.git\", rev = \"5b140361a\", features = [\"derive\"] }"
+        "serde = { git = \"ssh://git@github.com/serde-rs/serde.git\", rev = \"5b140361a\", features = [\n    \"derive\",\n] }"
     );
 
     test_dependency!(
         repository_with_http_url_rev_and_features,
         Dependency::Repository {
             name: "tokio".to_string(),
+            rename: None,
             url: "https://github.com/tokio-rs/tokio.git".to_string(),
-            branch: None,
-            rev: Some("75c0777".to_string()),
+            git_ref: Some(GitRef::Rev("75c0777".to_string())),
             features: vec!["io_std".to_string(), "io_utils".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "tokio=https://github.com/tokio-rs/tokio.git#rev=75c0777+io_std+io_utils",
         "https://github.com/tokio-rs/tokio.git#rev=75c0777+io_std+io_utils",
-        "tokio = { git = \"https://github.com/tokio-rs/tokio.git\", rev = \"75c0777\", features = [\"io_std\", \"io_utils\"] }"
+        "tokio = { git = \"https://github.com/tokio-rs/tokio.git\", rev = \"75c0777\", features = [\n    \"io_std\",\n    \"io_utils\",\n] }"
     );
 
     test_dependency!(
         repository_with_ssh_url_rev_and_features,
         Dependency::Repository {
             name: "serde".to_string(),
+            rename: None,
             url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
-            branch: None,
-            rev: Some("5b140361a".to_string()),
+            git_ref: Some(GitRef::Rev("5b140361a".to_string())),
             features: vec!["derive".to_string(), "alloc".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
         },
         "serde=ssh://git@github.com/serde-rs/serde.git#rev=5b140361a+derive+alloc",
         "ssh://git@github.com/serde-rs/serde.git#rev=5b140361a+derive+alloc",
-        "serde = { git = \"ssh://git@github.com/serde-rs/serde.git\", rev = \"5b140361a\", features = [\"derive\", \"alloc\"] }"
+        "serde = { git = \"ssh://git@github.com/serde-rs/serde.git\", rev = \"5b140361a\", features = [\n    \"derive\",\n    \"alloc\",\n] }"
+    );
+
+    test_dependency!(
+        dev_dependency,
+        Dependency::CratesIo {
+            name: "proptest".to_string(),
+            rename: None,
+            version: None,
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Dev,
+        },
+        "proptest%dev",
+        "proptest = \"*\""
+    );
+
+    test_dependency!(
+        dev_dependency_with_version_and_features,
+        Dependency::CratesIo {
+            name: "tokio".to_string(),
+            rename: None,
+            version: Some("1.0".to_string()),
+            features: vec!["macros".to_string(), "rt".to_string()],
+            default_features: true,
+            kind: DependencyKind::Dev,
+        },
+        "tokio=1.0+macros+rt%dev",
+        "tokio = { version = \"1.0\", features = [\"macros\", \"rt\"] }"
+    );
+
+    test_dependency!(
+        dependency_with_at_version,
+        Dependency::CratesIo {
+            name: "serde".to_string(),
+            rename: None,
+            version: Some("1.0.200".to_string()),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "serde@1.0.200",
+        "serde = \"1.0.200\""
+    );
+
+    test_dependency!(
+        dependency_with_at_comparison,
+        Dependency::CratesIo {
+            name: "serde".to_string(),
+            rename: None,
+            version: Some(">=1.0".to_string()),
+            features: vec!["derive".to_string()],
+            default_features: true,
+            kind: DependencyKind::Dev,
+        },
+        "dev:serde@>=1.0+derive",
+        "serde = { version = \">=1.0\", features = [\"derive\"] }"
+    );
+
+    test_dependency!(
+        repository_with_at,
+        Dependency::Repository {
+            name: "serde".to_string(),
+            rename: None,
+            url: "ssh://git@github.com/serde-rs/serde.git".to_string(),
+            git_ref: None,
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "serde@ssh://git@github.com/serde-rs/serde.git",
+        "ssh://git@github.com/serde-rs/serde.git",
+        "serde = { git = \"ssh://git@github.com/serde-rs/serde.git\" }"
+    );
+
+    test_dependency!(
+        renamed_dependency,
+        Dependency::CratesIo {
+            name: "serde_json".to_string(),
+            rename: Some("json".to_string()),
+            version: Some("1".to_string()),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "json:serde_json=1",
+        "json = { package = \"serde_json\", version = \"1\" }"
+    );
+
+    test_dependency!(
+        renamed_repository,
+        Dependency::Repository {
+            name: "wgpu".to_string(),
+            rename: Some("wgpu_next".to_string()),
+            url: "https://github.com/gfx-rs/wgpu".to_string(),
+            git_ref: Some(GitRef::Branch("trunk".to_string())),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "wgpu_next:wgpu=https://github.com/gfx-rs/wgpu#trunk",
+        "wgpu_next:https://github.com/gfx-rs/wgpu#trunk",
+        "wgpu_next = { package = \"wgpu\", git = \"https://github.com/gfx-rs/wgpu\", branch = \"trunk\" }"
+    );
+
+    test_dependency!(
+        renamed_dev_dependency,
+        Dependency::CratesIo {
+            name: "rand".to_string(),
+            rename: Some("rand_old".to_string()),
+            version: Some("0.7".to_string()),
+            features: vec!["small_rng".to_string()],
+            default_features: true,
+            kind: DependencyKind::Dev,
+        },
+        "dev:rand_old:rand=0.7+small_rng",
+        "rand_old:rand=0.7+small_rng%dev",
+        "rand_old = { package = \"rand\", version = \"0.7\", features = [\"small_rng\"] }"
+    );
+
+    test_dependency!(
+        crates_io_url,
+        Dependency::CratesIo {
+            name: "serde".to_string(),
+            rename: None,
+            version: None,
+            features: vec!["derive".to_string()],
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "serde=https://crates.io/crates/serde+derive",
+        "https://crates.io/crates/serde+derive",
+        "serde = { version = \"*\", features = [\"derive\"] }"
+    );
+
+    test_dependency!(
+        crates_io_url_with_version,
+        Dependency::CratesIo {
+            name: "serde_json".to_string(),
+            rename: None,
+            version: Some("1.0.117".to_string()),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        },
+        "serde_json=https://crates.io/crates/serde_json/1.0.117",
+        "https://crates.io/crates/serde_json/1.0.117",
+        "serde_json = \"1.0.117\""
+    );
+
+    test_dependency!(
+        lib_rs_url,
+        Dependency::CratesIo {
+            name: "cargo-temp".to_string(),
+            rename: None,
+            version: None,
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Dev,
+        },
+        "cargo-temp=https://lib.rs/crates/cargo-temp%dev",
+        "https://lib.rs/crates/cargo-temp%dev",
+        "cargo-temp = \"*\""
+    );
+
+    test_dependency!(
+        dev_dependency_with_prefix,
+        Dependency::CratesIo {
+            name: "insta".to_string(),
+            rename: None,
+            version: None,
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Dev,
+        },
+        "dev:insta",
+        "insta = \"*\""
     );
 
+    test_dependency!(
+        dev_dependency_with_prefix_version_and_features,
+        Dependency::CratesIo {
+            name: "proptest".to_string(),
+            rename: None,
+            version: Some("1".to_string()),
+            features: vec!["std".to_string()],
+            default_features: false,
+            kind: DependencyKind::Dev,
+        },
+        "dev:proptest=1++std",
+        "proptest = { version = \"1\", default-features = false, features = [\"std\"] }"
+    );
+
+    test_dependency!(
+        build_dependency_with_prefix,
+        Dependency::CratesIo {
+            name: "cc".to_string(),
+            rename: None,
+            version: Some("1".to_string()),
+            features: vec!["parallel".to_string()],
+            default_features: true,
+            kind: DependencyKind::Build,
+        },
+        "build:cc=1+parallel",
+        "cc = { version = \"1\", features = [\"parallel\"] }"
+    );
+
+    test_dependency!(
+        build_dependency,
+        Dependency::CratesIo {
+            name: "cc".to_string(),
+            rename: None,
+            version: Some("1".to_string()),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Build,
+        },
+        "cc=1%build",
+        "cc = \"1\""
+    );
+
+    test_dependency!(
+        dev_repository_with_http_url_and_branch,
+        Dependency::Repository {
+            name: "tokio".to_string(),
+            rename: None,
+            url: "https://github.com/tokio-rs/tokio.git".to_string(),
+            git_ref: Some(GitRef::Branch("compat".to_string())),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Dev,
+        },
+        "tokio=https://github.com/tokio-rs/tokio.git#branch=compat%dev",
+        "https://github.com/tokio-rs/tokio.git#branch=compat%dev",
+        "tokio = { git = \"https://github.com/tokio-rs/tokio.git\", branch = \"compat\" }"
+    );
+
+    fn crates_io(name: &str, version: Option<&str>, features: &[&str]) -> Dependency {
+        Dependency::CratesIo {
+            name: name.to_string(),
+            rename: None,
+            version: version.map(|x| x.to_string()),
+            features: features.iter().map(|x| x.to_string()).collect(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        }
+    }
+
+    #[test]
+    fn dedup_cli_wins() {
+        assert_eq!(
+            dedup_dependencies(
+                vec![
+                    crates_io("serde", Some("1.0.200"), &["derive"]),
+                    crates_io("anyhow", None, &[]),
+                    crates_io("serde", Some("1"), &["rc"]),
+                ],
+                DedupStrategy::CliWins,
+            )
+            .unwrap(),
+            [
+                crates_io("serde", Some("1"), &["rc"]),
+                crates_io("anyhow", None, &[]),
+            ]
+        );
+    }
+
+    #[test]
+    fn dedup_merge_features() {
+        assert_eq!(
+            dedup_dependencies(
+                vec![
+                    crates_io("serde", Some("1"), &["derive"]),
+                    crates_io("anyhow", None, &[]),
+                    crates_io("serde", Some("1"), &["rc", "derive"]),
+                    crates_io("anyhow", Some("1"), &[]),
+                    crates_io("anyhow", None, &["backtrace"]),
+                ],
+                DedupStrategy::MergeFeatures,
+            )
+            .unwrap(),
+            [
+                crates_io("serde", Some("1"), &["derive", "rc"]),
+                crates_io("anyhow", Some("1"), &["backtrace"]),
+            ]
+        );
+
+        assert_eq!(
+            dedup_dependencies(
+                vec![
+                    parse_dependency("https://github.com/a/a#v1+foo").unwrap(),
+                    parse_dependency("https://github.com/a/a#v1+bar").unwrap(),
+                ],
+                DedupStrategy::MergeFeatures,
+            )
+            .unwrap(),
+            [parse_dependency("https://github.com/a/a#v1+foo,bar").unwrap()]
+        );
+
+        for dependencies in [
+            ["serde=1.0.200", "serde=1"],
+            ["serde=1", "serde=2"],
+            ["a=1", "https://github.com/a/a"],
+            ["https://github.com/a/a#v1", "https://github.com/a/a#v2"],
+            ["https://github.com/a/a", "https://github.com/b/a"],
+        ] {
+            let dependencies = dependencies
+                .iter()
+                .map(|x| parse_dependency(x).unwrap())
+                .collect();
+            let res = dedup_dependencies(dependencies, DedupStrategy::MergeFeatures);
+            assert!(res.is_err(), "{res:?}");
+        }
+    }
+
+    #[test]
+    fn dedup_renamed() {
+        let dependencies = vec![
+            parse_dependency("rand=0.8").unwrap(),
+            parse_dependency("rand_old:rand=0.7").unwrap(),
+        ];
+
+        assert_eq!(
+            dedup_dependencies(dependencies.clone(), DedupStrategy::Error).unwrap(),
+            dependencies
+        );
+    }
+
+    #[test]
+    fn dedup_error() {
+        let res = dedup_dependencies(
+            vec![
+                crates_io("serde", Some("1"), &[]),
+                crates_io("serde", Some("1"), &[]),
+            ],
+            DedupStrategy::Error,
+        );
+        assert!(res.is_err(), "{res:?}");
+    }
+
+    #[test]
+    fn path_dependency() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .canonicalize()
+            .unwrap();
+        let dependency = Dependency::Path {
+            name: "cargo-temp".to_string(),
+            rename: None,
+            path: path.clone(),
+            features: Vec::new(),
+            default_features: true,
+            kind: DependencyKind::Normal,
+        };
+
+        assert_eq!(
+            parse_dependency(&format!("path:{}", path.display())).unwrap(),
+            dependency
+        );
+        assert_eq!(
+            parse_dependency(&format!("cargo-temp={}", path.display())).unwrap(),
+            dependency
+        );
+        assert_eq!(
+            format_dependency(&dependency, DEFAULT_WRAP_WIDTH),
+            format!("cargo-temp = {{ path = {:?} }}", path.to_string_lossy())
+        );
+        assert_eq!(
+            parse_manifest_dependencies(&format!(
+                "[dependencies]\ncargo-temp = {{ path = {:?} }}",
+                path.to_string_lossy()
+            ))
+            .unwrap(),
+            [dependency]
+        );
+
+        let dependency =
+            parse_dependency(&format!("mylib=path:{}/src!default+foo", path.display())).unwrap();
+        assert_eq!(
+            dependency,
+            Dependency::Path {
+                name: "mylib".to_string(),
+                rename: None,
+                path: path.join("src"),
+                features: vec!["foo".to_string()],
+                default_features: false,
+                kind: DependencyKind::Normal,
+            }
+        );
+        assert_eq!(
+            format_dependency(&dependency, DEFAULT_WRAP_WIDTH),
+            format!(
+                "mylib = {{ path = {:?}, default-features = false, features = [\"foo\"] }}",
+                path.join("src").to_string_lossy()
+            )
+        );
+        assert_eq!(
+            cargo_add_args(&dependency),
+            [
+                "--path",
+                &path.join("src").to_string_lossy(),
+                "--no-default-features",
+                "--features",
+                "foo"
+            ]
+        );
+
+        assert!(parse_dependency("path:/does/not/exist").is_err());
+        assert!(parse_dependency(&format!("path:{}/Cargo.toml", path.display())).is_err());
+        assert_eq!(
+            parse_dependency("./main.rs").unwrap().name(),
+            "./main.rs",
+            "a Rust file is not a path dependency"
+        );
+    }
+
+    #[test]
+    fn invalid_dependencies() {
+        let args = [
+            "anyhow",
+            "http://localhost",
+            "serde=1",
+            "a!default!!default",
+        ]
+        .map(str::to_string);
+
+        let err = parse_dependencies(&args, false).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "invalid dependencies:\n  `http://localhost`: could not guess name of crate in URL\n  \
+            `a!default!!default`: could not parse dependency"
+        );
+
+        assert_eq!(
+            parse_dependencies(&args, true).unwrap(),
+            [
+                crates_io("anyhow", None, &[]),
+                crates_io("serde", Some("1"), &[])
+            ]
+        );
+    }
+
+    #[test]
+    fn crate_page_url_mismatch() {
+        assert!(parse_dependency("serde=https://crates.io/crates/tokio").is_err());
+        assert!(parse_dependency("https://crates.io/crates/serde#rev=abc").is_err());
+    }
+
+    #[test]
+    fn git_refs() {
+        let git_ref = |s: &str| match parse_dependency(s).unwrap() {
+            Dependency::Repository { git_ref, .. } => git_ref,
+            dependency => panic!("{dependency:?}"),
+        };
+
+        for (s, expected) in [
+            ("#75c0777", GitRef::Rev("75c0777".to_string())),
+            ("#v1.0.0", GitRef::Branch("v1.0.0".to_string())),
+            ("#cafe", GitRef::Branch("cafe".to_string())),
+            ("#branch=deadbeef", GitRef::Branch("deadbeef".to_string())),
+            ("#tag=v1.0.0", GitRef::Tag("v1.0.0".to_string())),
+        ] {
+            assert_eq!(
+                git_ref(&format!("https://github.com/a/a{s}")),
+                Some(expected),
+                "{s}"
+            );
+        }
+        assert_eq!(git_ref("https://github.com/a/a"), None);
+
+        // A repository has at most one reference, so the formatter can't write several of them.
+        assert_eq!(
+            format_dependency(
+                &parse_dependency("https://github.com/a/a#tag=v1").unwrap(),
+                DEFAULT_WRAP_WIDTH
+            ),
+            "a = { git = \"https://github.com/a/a\", tag = \"v1\" }"
+        );
+
+        for s in [
+            "https://github.com/a/a#branch=main#tag=v1",
+            "https://github.com/a/a#tag=v1#rev=75c0777",
+            "https://github.com/a/a#commit=75c0777",
+            "https://github.com/a/a#tag=",
+            "https://github.com/a/a#",
+        ] {
+            let res = parse_dependency(s);
+            assert!(res.is_err(), "{s}: {res:?}");
+        }
+    }
+
+    #[test]
+    fn windows_drive_is_not_renamed() {
+        for s in [r"C:\x\main.rs", "C:/deps/foo", "dev:D:/deps/foo"] {
+            assert_eq!(split_rename(s).unwrap(), (s.to_string(), None), "{s}");
+        }
+
+        assert_eq!(
+            parse_dependency(r"C:\x\main.rs").unwrap().name(),
+            r"C:\x\main.rs"
+        );
+        assert_eq!(
+            split_rename("c:serde").unwrap(),
+            ("serde".to_string(), Some("c".to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_rename() {
+        for s in ["foo:", "a:b:c", "dev:a:", "a:=1", "a:+feature"] {
+            let res = parse_dependency(s);
+            assert!(res.is_err(), "{s}: {res:?}");
+        }
+
+        for s in [
+            "json:serde_json",
+            "json:serde_json=1",
+            "json:serde_json+raw_value",
+            "a:https://github.com/a/a",
+        ] {
+            assert!(parse_dependency(s).is_ok(), "{s}");
+        }
+    }
+
+    #[test]
+    fn conflicting_kinds() {
+        assert_eq!(
+            parse_dependency("dev:insta%dev").unwrap(),
+            parse_dependency("insta%dev").unwrap()
+        );
+        assert!(parse_dependency("dev:cc%build").is_err());
+        assert!(parse_dependency("build:proptest%dev").is_err());
+    }
+
+    #[test]
+    fn dedup_different_kinds() {
+        let dependencies = vec![
+            crates_io("serde", None, &[]),
+            parse_dependency("serde%dev").unwrap(),
+        ];
+
+        assert_eq!(
+            dedup_dependencies(dependencies.clone(), DedupStrategy::Error).unwrap(),
+            dependencies
+        );
+    }
+
+    #[test]
+    fn manifest_dependencies() {
+        let manifest = r#"
+            [dependencies]
+            anyhow = "1.0"
+            serde = { version = "1", features = ["derive"] }
+            tokio = { git = "https://github.com/tokio-rs/tokio.git", branch = "compat", default-features = false, features = ["rt"] }
+            json = { package = "serde_json", version = "1" }
+            serde_json = { git = "https://github.com/serde-rs/json", tag = "v1.0.117" }
+
+            [dev-dependencies]
+            proptest = { version = "1.4", default-features = false }
+
+            [build-dependencies]
+            cc = "1"
+        "#;
+
+        assert_eq!(
+            parse_manifest_dependencies(manifest)
+                .unwrap()
+                .iter()
+                .map(|x| (x.kind(), format_dependency(x, DEFAULT_WRAP_WIDTH)))
+                .collect::<Vec<_>>(),
+            [
+                (DependencyKind::Normal, "anyhow = \"1.0\""),
+                (
+                    DependencyKind::Normal,
+                    "json = { package = \"serde_json\", version = \"1\" }"
+                ),
+                (
+                    DependencyKind::Normal,
+                    "serde = { version = \"1\", features = [\"derive\"] }"
+                ),
+                (
+                    DependencyKind::Normal,
+                    "serde_json = { git = \"https://github.com/serde-rs/json\", tag = \"v1.0.117\" }"
+                ),
+                (
+                    DependencyKind::Normal,
+                    "tokio = { git = \"https://github.com/tokio-rs/tokio.git\", branch = \"compat\", default-features = false, features = [\n    \"rt\",\n] }"
+                ),
+                (
+                    DependencyKind::Dev,
+                    "proptest = { version = \"1.4\", default-features = false }"
+                ),
+                (DependencyKind::Build, "cc = \"1\""),
+            ]
+            .map(|(kind, line)| (kind, line.to_string()))
+        );
+    }
+
+    #[test]
+    fn default_features() {
+        let without_default_features = Dependency::CratesIo {
+            name: "tokio".to_string(),
+            rename: None,
+            version: Some("1.0".to_string()),
+            features: vec!["rt".to_string()],
+            default_features: false,
+            kind: DependencyKind::Normal,
+        };
+
+        for s in [
+            "tokio=1.0!default+rt",
+            "tokio=1.0++rt",
+            "tokio=1.0!default++rt",
+        ] {
+            assert_eq!(
+                parse_dependency(s).unwrap(),
+                without_default_features,
+                "{s}"
+            );
+        }
+
+        assert_eq!(
+            parse_dependency("tokio=1.0!!default+rt").unwrap(),
+            parse_dependency("tokio=1.0+rt").unwrap()
+        );
+        assert_eq!(
+            format_dependency(
+                &parse_dependency("tokio!default").unwrap(),
+                DEFAULT_WRAP_WIDTH
+            ),
+            "tokio = { version = \"*\", default-features = false }"
+        );
+        assert_eq!(
+            parse_dependency("tokio++rt%dev").unwrap(),
+            Dependency::CratesIo {
+                name: "tokio".to_string(),
+                rename: None,
+                version: None,
+                features: vec!["rt".to_string()],
+                default_features: false,
+                kind: DependencyKind::Dev,
+            }
+        );
+        assert_eq!(
+            parse_dependency("https://github.com/tokio-rs/tokio#branch=compat!default+io_std")
+                .unwrap(),
+            Dependency::Repository {
+                name: "tokio".to_string(),
+                rename: None,
+                url: "https://github.com/tokio-rs/tokio".to_string(),
+                git_ref: Some(GitRef::Branch("compat".to_string())),
+                features: vec!["io_std".to_string()],
+                default_features: false,
+                kind: DependencyKind::Normal,
+            }
+        );
+
+        for s in [
+            "tokio!!default++rt",
+            "tokio++",
+            "tokio+",
+            "tokio!default!default",
+        ] {
+            assert!(parse_dependency(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn cargo_add() {
+        assert_eq!(
+            cargo_add_args(&parse_dependency("anyhow").unwrap()),
+            ["anyhow"]
+        );
+        assert_eq!(
+            cargo_add_args(&parse_dependency("json:serde_json=1").unwrap()),
+            ["serde_json@1", "--rename", "json"]
+        );
+        assert_eq!(
+            cargo_add_args(&parse_dependency("tokio=1.0+rt,macros%dev").unwrap()),
+            ["tokio@1.0", "--features", "rt,macros", "--dev"]
+        );
+        assert_eq!(
+            cargo_add_args(
+                &parse_dependency("serde=https://github.com/serde-rs/serde#branch=master+derive")
+                    .unwrap()
+            ),
+            [
+                "serde",
+                "--git",
+                "https://github.com/serde-rs/serde",
+                "--branch",
+                "master",
+                "--features",
+                "derive"
+            ]
+        );
+    }
+
+    #[test]
+    fn count_dependencies() {
+        let manifest = r#"
+            [package]
+            name = "foo"
+
+            [dependencies]
+            anyhow = "1"
+            serde = { version = "1", features = ["derive"] }
+
+            [dev-dependencies]
+            proptest = "1"
+        "#;
+
+        assert_eq!(count_manifest_dependencies(manifest).unwrap(), 3);
+        assert_eq!(count_manifest_dependencies("[package]").unwrap(), 0);
+        assert!(count_manifest_dependencies("dependencies = 1").is_err());
+    }
+
+    #[test]
+    fn manifest_dependencies_without_table() {
+        assert_eq!(
+            parse_manifest_dependencies("anyhow = \"1\"\nrand = { version = \"0.8\" }").unwrap(),
+            [
+                Dependency::CratesIo {
+                    name: "anyhow".to_string(),
+                    rename: None,
+                    version: Some("1".to_string()),
+                    features: Vec::new(),
+                    default_features: true,
+                    kind: DependencyKind::Normal,
+                },
+                Dependency::CratesIo {
+                    name: "rand".to_string(),
+                    rename: None,
+                    version: Some("0.8".to_string()),
+                    features: Vec::new(),
+                    default_features: true,
+                    kind: DependencyKind::Normal,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dependencies_file() {
+        let expected = [
+            crates_io("anyhow", Some("1.0"), &[]),
+            Dependency::CratesIo {
+                name: "proptest".to_string(),
+                rename: None,
+                version: Some("1".to_string()),
+                features: Vec::new(),
+                default_features: true,
+                kind: DependencyKind::Dev,
+            },
+        ];
+
+        assert_eq!(
+            parse_dependencies_file(
+                "[dependencies]\nanyhow = \"1.0\"\n\n[dev-dependencies]\nproptest = \"1\""
+            )
+            .unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse_dependencies_file("# Scratch\nanyhow=1.0\n\nproptest=1%dev\n").unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn dependencies_file_detection() {
+        // Valid TOML without a dependency table is a list of dependencies.
+        assert_eq!(
+            parse_dependencies_file("anyhow=1.0").unwrap(),
+            [crates_io("anyhow", Some("1.0"), &[])]
+        );
+        assert!(parse_dependencies_file("[dependencies]\nanyhow = 1").is_err());
+    }
+
+    #[test]
+    fn could_not_parse_manifest() {
+        for manifest in [
+            "[dependencies",
+            "[dependencies]\nfoo = 1",
+            "[dependencies]\nfoo = { path = \"does/not/exist\" }",
+            "[dependencies]\nfoo = { path = \"Cargo.toml\" }",
+            "[dependencies]\nfoo = { git = \"https://a.b/foo\", branch = \"a\", tag = \"b\" }",
+            "[dependencies]\nfoo = { version = \"1\", features = \"bar\" }",
+        ] {
+            let res = parse_manifest_dependencies(manifest);
+            assert!(res.is_err(), "{manifest}: {res:?}");
+        }
+    }
+
     #[test]
     fn could_not_parse() {
         let res = parse_dependency("http://localhost");
         assert!(res.is_err(), "{res:?}");
     }
+
+    #[test]
+    fn long_features() {
+        // `a = { version = "*", features = ["` and `"] }` are 38 characters.
+        let feature = "f".repeat(DEFAULT_WRAP_WIDTH - 38);
+        assert_eq!(
+            format_dependency(&crates_io("a", None, &[&feature]), DEFAULT_WRAP_WIDTH),
+            format!("a = {{ version = \"*\", features = [\"{feature}\"] }}")
+        );
+
+        let feature = "f".repeat(DEFAULT_WRAP_WIDTH - 37);
+        assert_eq!(
+            format_dependency(&crates_io("a", None, &[&feature]), DEFAULT_WRAP_WIDTH),
+            format!("a = {{ version = \"*\", features = [\n    \"{feature}\",\n] }}")
+        );
+
+        let dependency = parse_dependency(&format!(
+            "a=https://github.com/a/a#branch=main++{},b",
+            "f".repeat(DEFAULT_WRAP_WIDTH)
+        ))
+        .unwrap();
+        let line = format_dependency(&dependency, DEFAULT_WRAP_WIDTH);
+        assert!(line.ends_with(",\n    \"b\",\n] }"), "{line}");
+        assert_eq!(
+            parse_manifest_dependencies(&format!("[dependencies]\n{line}")).unwrap(),
+            vec![dependency]
+        );
+
+        let dependency = crates_io("tokio", Some("1"), &["macros", "rt"]);
+        assert_eq!(
+            format_dependency(&dependency, 60),
+            "tokio = { version = \"1\", features = [\"macros\", \"rt\"] }"
+        );
+        assert_eq!(
+            format_dependency(&dependency, 40),
+            "tokio = { version = \"1\", features = [\n    \"macros\",\n    \"rt\",\n] }"
+        );
+    }
 }
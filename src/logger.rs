@@ -0,0 +1,193 @@
+use crate::config::Config;
+use anyhow::{Context, Result};
+use std::{
+    ffi::OsString,
+    fs::{create_dir_all, remove_file, rename, File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// Size of the log file above which it is rotated, 1 MiB.
+const DEFAULT_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Number of rotated log files kept.
+const DEFAULT_MAX_OLD: usize = 3;
+
+/// File receiving the logs, set once the config file has been read.
+pub type LogFile = Arc<Mutex<Option<File>>>;
+
+/// Initialize the logger, the logs are written to stderr and to the returned log file.
+///
+/// With `quiet`, only the warnings and the errors are written to stderr.
+pub fn init(quiet: bool) -> LogFile {
+    let file = LogFile::default();
+
+    let stderr_level = if quiet {
+        log::LevelFilter::Warn
+    } else {
+        log::LevelFilter::Info
+    };
+
+    let logger = Logger {
+        stderr: builder(stderr_level, false)
+            .target(env_logger::Target::Stderr)
+            .build(),
+        file: builder(log::LevelFilter::Info, true)
+            .target(env_logger::Target::Pipe(Box::new(LogWriter {
+                file: file.clone(),
+            })))
+            .build(),
+    };
+
+    log::set_max_level(log::LevelFilter::Info);
+    log::set_boxed_logger(Box::new(logger)).expect("the logger is already initialized");
+
+    file
+}
+
+/// The lines of the log file are prefixed with the time they were logged.
+fn builder(level: log::LevelFilter, timestamp: bool) -> env_logger::Builder {
+    let mut builder = env_logger::Builder::from_default_env();
+    builder
+        .format(move |buf, record| {
+            if timestamp {
+                write!(buf, "{} ", buf.timestamp_seconds())?;
+            }
+            writeln!(buf, "[{} cargo-temp] {}", record.level(), record.args())
+        })
+        .filter(Some("cargo_temp"), level);
+    builder
+}
+
+/// Open the log file.
+///
+/// With `rotate`, a file bigger than `log_file_max_size` is renamed with the `.1` extension, the
+/// previous ones are shifted to `.2`, `.3`... and only `log_file_max_old` of them are kept.
+pub fn open(path: &Path, config: &Config, rotate: bool) -> Result<File> {
+    if let Some(parent) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+        create_dir_all(parent)
+            .with_context(|| format!("cannot create log directory `{}`", parent.display()))?;
+    }
+
+    let max_size = config.log_file_max_size.unwrap_or(DEFAULT_MAX_SIZE);
+    if rotate && path.metadata().is_ok_and(|x| x.len() > max_size) {
+        rotate_files(path, config.log_file_max_old.unwrap_or(DEFAULT_MAX_OLD))
+            .context("cannot rotate log file")?;
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("cannot open log file `{}`", path.display()))
+}
+
+/// Shift the rotated files and rename the log file, the oldest one is removed.
+fn rotate_files(path: &Path, max_old: usize) -> io::Result<()> {
+    let old = |n: usize| {
+        let mut old = OsString::from(path.as_os_str());
+        old.push(format!(".{n}"));
+        old
+    };
+
+    if max_old == 0 {
+        return remove_file(path);
+    }
+
+    let oldest = old(max_old);
+    if Path::new(&oldest).exists() {
+        remove_file(oldest)?;
+    }
+
+    for n in (1..max_old).rev() {
+        if Path::new(&old(n)).exists() {
+            rename(old(n), old(n + 1))?;
+        }
+    }
+
+    rename(path, old(1))
+}
+
+/// Logs to stderr and to the log file, each with its own level.
+struct Logger {
+    stderr: env_logger::Logger,
+    file: env_logger::Logger,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.stderr.enabled(metadata) || self.file.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        self.stderr.log(record);
+        self.file.log(record);
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+        self.file.flush();
+    }
+}
+
+struct LogWriter {
+    file: LogFile,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            file.write_all(buf)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            file.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{read_to_string, write};
+
+    #[test]
+    fn rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cargo-temp.log");
+        let mut config = Config::new().unwrap();
+        config.log_file_max_size = Some(4);
+        config.log_file_max_old = Some(2);
+
+        // The file is kept while it's under the limit or outside of a session.
+        write(&path, "1234").unwrap();
+        open(&path, &config, true).unwrap();
+        write(&path, "first").unwrap();
+        open(&path, &config, false).unwrap();
+        assert_eq!(read_to_string(&path).unwrap(), "first");
+
+        for content in ["second", "third"] {
+            open(&path, &config, true).unwrap();
+            write(&path, content).unwrap();
+        }
+        open(&path, &config, true).unwrap();
+
+        assert_eq!(read_to_string(&path).unwrap(), "");
+        assert_eq!(
+            read_to_string(dir.path().join("cargo-temp.log.1")).unwrap(),
+            "third"
+        );
+        assert_eq!(
+            read_to_string(dir.path().join("cargo-temp.log.2")).unwrap(),
+            "second"
+        );
+        assert!(!dir.path().join("cargo-temp.log.3").exists());
+    }
+}
@@ -1,6 +1,6 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use std::{env, fs::create_dir, io::Write};
+use std::{env, process::ExitCode, sync::atomic::Ordering};
 
 #[cfg(windows)]
 mod binding;
@@ -8,17 +8,22 @@ mod binding;
 mod cli;
 mod config;
 mod dependency;
+mod logger;
 mod project;
+mod snapshot;
 mod subprocess;
+#[cfg(feature = "update-check")]
+mod update;
 
-use crate::{cli::Cli, config::Config, project::Project};
-
-fn main() -> Result<()> {
-    env_logger::builder()
-        .format(|buf, record| writeln!(buf, "[{} cargo-temp] {}", record.level(), record.args()))
-        .filter(Some("cargo_temp"), log::LevelFilter::Info)
-        .init();
+use crate::{
+    cli::{Cli, Command},
+    config::{Config, LogFileMode},
+    project::Project,
+    snapshot::Snapshot,
+    subprocess::SubProcess,
+};
 
+fn main() -> Result<ExitCode> {
     // Parse the command line input.
     let mut args = env::args().peekable();
     let command = args.next();
@@ -26,9 +31,109 @@ fn main() -> Result<()> {
 
     let cli = Cli::parse_from(command.into_iter().chain(args));
 
-    // Read configuration from disk or generate a default one.
-    let config = Config::get_or_create()?;
-    let _ = create_dir(&config.temporary_project_dir);
+    let log_file = logger::init(cli.quiet);
+
+    // Read configuration from disk or generate a default one, a dry run doesn't write anything.
+    let mut config = if cli.dry_run {
+        Config::get()?
+    } else {
+        Config::get_or_create()?
+    };
+
+    if let Err(err) = cli.check_release(config.always_build) {
+        err.exit();
+    }
+
+    if let Some(path) = config.log_file.as_deref().filter(|_| !cli.dry_run) {
+        // Only the sessions rotate the file, not `--config`, `--list` or `--diff`.
+        let rotate = config.log_file_mode == LogFileMode::Rotate && cli.action().is_none();
+        *log_file.lock().unwrap() = Some(logger::open(path, &config, rotate)?);
+    }
+
+    match cli.action() {
+        Some(Command::Config { path }) => {
+            let config_file_path = Config::file_path()?;
+
+            if path {
+                println!("{}", config_file_path.display());
+            } else {
+                print!(
+                    "# {}\n{}",
+                    config_file_path.display(),
+                    toml::ser::to_string(&config)?
+                );
+            }
+
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::List) => {
+            Project::list(&config)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Diff { path }) => {
+            let path = path.unwrap_or_else(|| ".".into());
+            let changes = Snapshot::load(&path)?.diff(&Snapshot::take(&path)?);
+
+            if changes.is_empty() {
+                println!("No changes");
+            } else {
+                print!("{changes}");
+            }
+
+            return Ok(ExitCode::SUCCESS);
+        }
+        None => {}
+    }
+
+    if cli.bare {
+        config.vcs = Some("none".to_string());
+        config.welcome_message = false;
+        config.prompt = false;
+    }
+
+    if cli.quiet {
+        config.welcome_message = false;
+    }
+
+    if cli.offline || config.offline {
+        env::set_var("CARGO_NET_OFFLINE", "true");
+    }
+
+    // The cache of the latest version is written by the check.
+    #[cfg(feature = "update-check")]
+    if config.check_for_updates && !cli.dry_run && !project::is_offline() {
+        update::check_for_updates();
+    }
+
+    #[cfg(not(feature = "update-check"))]
+    if config.check_for_updates {
+        log::warn!(
+            "cannot check for updates, cargo-temp is built without the `update-check` feature"
+        );
+    }
+
+    if cli.no_subprocesses {
+        config.subprocesses.clear();
+    }
+
+    config.subprocesses.extend(
+        cli.exec
+            .iter()
+            .map(|command| SubProcess::new(command, false))
+            .chain(
+                cli.exec_fg
+                    .iter()
+                    .map(|command| SubProcess::new(command, true)),
+            ),
+    );
+
+    if !cli.dry_run {
+        project::create_temporary_project_dir(&config.temporary_project_dir)?;
+    }
+
+    // Let the project be deleted when cargo-temp is interrupted.
+    ctrlc::set_handler(|| project::INTERRUPTED.store(true, Ordering::SeqCst))
+        .context("cannot set the interruption handler")?;
 
     Project::execute(cli, config)
 }
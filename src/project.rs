@@ -1,333 +1,1182 @@
 use crate::{
-    cli::Cli,
-    config::{Config, Depth},
-    dependency::format_dependency,
-    subprocess::{kill_subprocesses, start_subprocesses, Child},
+    cli::{Cli, Format},
+    config::{Config, Depth, Severity},
+    dependency::{
+        cargo_add_args, count_manifest_dependencies, crate_name, dedup_dependencies,
+        format_dependency, parse_dependencies, parse_dependencies_file, parse_dependency,
+        parse_manifest_dependencies, Dependency, DependencyKind, DEFAULT_WRAP_WIDTH,
+    },
+    snapshot::Snapshot,
+    subprocess::{kill_subprocesses, start_subprocesses, BackgroundCheck, Child},
 };
 use anyhow::{bail, ensure, Context, Result};
+use flate2::{write::GzEncoder, Compression};
 use std::{
+    borrow::Cow,
     env,
-    fs::{create_dir_all, remove_file, rename, write, OpenOptions},
-    io::{stdin, Write},
+    ffi::{OsStr, OsString},
+    fs::{create_dir_all, read_dir, remove_file, rename, write, File, OpenOptions},
+    io::{read_to_string, stdin, Write},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, ExitCode},
+    sync::atomic::{AtomicBool, Ordering},
 };
 
+/// Set when cargo-temp receives `Ctrl+C` (or `SIGTERM` and `SIGHUP` on Unix).
+///
+/// The spawned processes receive the signal too, the project is deleted once they exit.
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
 pub struct Project(tempfile::TempDir);
 
 impl Project {
-    pub fn execute(cli: Cli, config: Config) -> Result<()> {
-        let project = Self::temporary(
-            cli.clone(),
-            &config.temporary_project_dir,
-            config.git_repo_depth.as_ref(),
-            config.vcs.as_deref(),
-        )?;
+    pub fn execute(mut cli: Cli, config: Config) -> Result<ExitCode> {
+        cli.dependencies = parse_dependencies(&cli.dependency_args, config.skip_invalid_deps)?;
 
-        let project_path = project.0.path();
+        // A path to a Rust file is parsed as a dependency without version.
+        if let Some(Dependency::CratesIo {
+            name,
+            version: None,
+            features,
+            kind: DependencyKind::Normal,
+            ..
+        }) = cli.dependencies.first()
+        {
+            let path = Path::new(name);
 
-        let delete_file = project_path.join("TO_DELETE");
-        write(
-            &delete_file,
-            "Delete this file if you want to preserve this project",
-        )?;
+            if features.is_empty() && path.extension().is_some_and(|x| x == "rs") && path.is_file()
+            {
+                ensure!(
+                    cli.code.is_none()
+                        && cli.no_std.is_none()
+                        && !cli.lib
+                        && cli.git.is_none()
+                        && !cli.worktree(),
+                    "a Rust file can't be used with `--code`, `--no-std`, `--lib`, `--git` or \
+                    `--worktree`"
+                );
 
-        let mut subprocesses = start_subprocesses(&config, project_path);
+                cli.script = Some(path.to_path_buf());
+                cli.dependencies.remove(0);
+            }
+        }
 
-        log::info!("Temporary project created at: {}", project_path.display());
+        validate(&cli, &config)?;
 
-        if config.welcome_message {
-            println!(
-                "\nTo preserve the project when exiting the shell, don't forget to delete the \
-            `TO_DELETE` file.\nTo exit the project, you can type \"exit\" or use `Ctrl+D`"
-            );
+        // Dependencies from the arguments come last so they win over the templates.
+        let mut template_dependencies = Vec::new();
+        for name in cli.template.iter() {
+            let template = config
+                .templates
+                .get(name)
+                .with_context(|| format!("cannot find template `{name}`"))?;
+
+            for dependency in template {
+                template_dependencies.push(parse_dependency(dependency).with_context(|| {
+                    format!("invalid dependency `{dependency}` in template `{name}`")
+                })?);
+            }
         }
+        template_dependencies.append(&mut cli.dependencies);
+        cli.dependencies = template_dependencies;
 
-        let res = {
-            let mut shell_process = match config.editor {
-                None => {
-                    #[cfg(unix)]
-                    let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+        if let Some(path) = cli.deps_file.as_deref() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("cannot read dependencies file `{}`", path.display()))?;
 
-                    #[cfg(windows)]
-                    let shell = env::var("COMSPEC").unwrap_or_else(|_| "cmd".to_string());
+            let mut dependencies = parse_dependencies_file(&content)?;
+            dependencies.append(&mut cli.dependencies);
+            cli.dependencies = dependencies;
+        }
 
-                    Command::new(shell)
-                }
-                Some(ref editor) => {
-                    let mut ide_process = std::process::Command::new(editor);
-                    ide_process
-                        .args(config.editor_args.iter().flatten())
-                        .arg(project_path);
-                    ide_process
-                }
+        if let Some(manifest) = cli.from_manifest.as_deref() {
+            let manifest = if manifest == "-" {
+                read_to_string(stdin()).context("cannot read manifest from stdin")?
+            } else {
+                manifest.to_string()
             };
 
-            if env::var("CARGO_TARGET_DIR").is_err() {
-                if let Some(path) = &config.cargo_target_dir {
-                    env::set_var("CARGO_TARGET_DIR", path);
+            let mut dependencies = parse_manifest_dependencies(&manifest)?;
+            dependencies.append(&mut cli.dependencies);
+            cli.dependencies = dependencies;
+        }
+
+        // The default dependencies are the same for every project.
+        let first_dependency = cli.dependencies.first().map(|x| x.name().to_string());
+
+        if !cli.no_default_deps {
+            let mut dependencies = config
+                .default_dependencies
+                .iter()
+                .map(|dependency| {
+                    parse_dependency(dependency)
+                        .with_context(|| format!("invalid default dependency `{dependency}`"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            dependencies.append(&mut cli.dependencies);
+            cli.dependencies = dependencies;
+        }
+
+        cli.dependencies = dedup_dependencies(cli.dependencies, config.dedup_strategy)?;
+
+        if let Some(max) = config.max_dependencies {
+            let count = cli.dependencies.len();
+
+            if count > max && !cli.force {
+                match config.max_dependencies_severity {
+                    Severity::Warn => log::warn!(
+                        "{count} dependencies exceed the `max_dependencies` limit of {max}"
+                    ),
+                    Severity::Error => bail!(
+                        "{count} dependencies exceed the `max_dependencies` limit of {max}, \
+                        use `--force` to proceed"
+                    ),
+                }
+            }
+        }
+
+        // Name used for the directory of the project, the crate name is only set by `--bin-name`
+        // or `--name`.
+        let project_name = cli
+            .project_name
+            .clone()
+            .or(first_dependency.filter(|_| config.name_from_first_dependency));
+
+        if let Some(name) = project_name.as_deref() {
+            let preserved_path = preserved_project_dir(&cli, &config)
+                .unwrap_or(&config.temporary_project_dir)
+                .join(name);
+
+            if preserved_path.exists() {
+                let message = format!(
+                    "a project already exists at {}, it can't be preserved with the same name",
+                    preserved_path.display()
+                );
+
+                if cli.exact_name {
+                    bail!(message);
+                } else {
+                    log::warn!("{message}");
                 }
             }
+        }
+
+        if cli.bench.is_some()
+            && !cli
+                .dependencies
+                .iter()
+                .any(|x| x.name() == "criterion" && x.kind() == DependencyKind::Dev)
+        {
+            cli.dependencies.push(Dependency::CratesIo {
+                name: "criterion".to_string(),
+                rename: None,
+                version: None,
+                features: Vec::new(),
+                default_features: true,
+                kind: DependencyKind::Dev,
+            });
+        }
+
+        if let Some(Some(panic_crate)) = &cli.no_std {
+            if !cli
+                .dependencies
+                .iter()
+                .any(|x| x.name() == panic_crate.name() && x.kind() == panic_crate.kind())
+            {
+                cli.dependencies.push(panic_crate.clone());
+            }
+        }
+
+        if cli.dry_run {
+            Self::dry_run(&cli, &config, project_name.as_deref())?;
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let project = Self::temporary(cli.clone(), &config, project_name.as_deref())?;
+
+        if cli.no_shell {
+            let project_path = project.0.into_path();
+            let project_path = project_path.canonicalize().unwrap_or(project_path);
+
+            match cli.format {
+                Format::Human => println!("{}", project_path.display()),
+                Format::Json => println!(
+                    "{}",
+                    json_report(
+                        &project_path,
+                        crate_name(&project_path),
+                        &cli,
+                        &config,
+                        true
+                    )?
+                ),
+            }
+
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        if cli.run {
+            return project.run(&cli, &config, project_name.as_deref());
+        }
+
+        project.start_shell(cli, config, project_name.as_deref())?;
+
+        Ok(ExitCode::SUCCESS)
+    }
+
+    /// Run the project with `cargo run` and delete it, unless `--keep` is used.
+    ///
+    /// Returns the exit code of the binary.
+    fn run(self, cli: &Cli, config: &Config, project_name: Option<&str>) -> Result<ExitCode> {
+        let mut command = Command::new("cargo");
+        command
+            .current_dir(self.0.path())
+            .arg("run")
+            .args(cli.release.then_some("--release"))
+            .args(cli.locked.then_some("--locked"))
+            .args(&cli.run_args);
+
+        if let Some(path) = cargo_target_dir(cli, config, self.0.path())? {
+            command.env("CARGO_TARGET_DIR", path);
+        }
+
+        let status = command.status().context("Could not start cargo")?;
+        let crate_name = crate_name(self.0.path());
+
+        let (path, preserved) = if cli.keep {
+            let tmp_dir = self.preserve_dir(project_name, preserved_project_dir(cli, config))?;
+
+            if cli.format == Format::Human {
+                log::info!("Project directory_preserved_at: {}", tmp_dir.display());
+            }
+
+            (tmp_dir, true)
+        } else {
+            (self.0.path().to_path_buf(), false)
+        };
+
+        // The standard output belongs to the binary.
+        if cli.format == Format::Json {
+            eprintln!(
+                "{}",
+                json_report(&path, crate_name, cli, config, preserved)?
+            );
+        }
+
+        Ok(exit_code(status.code()))
+    }
+
+    fn start_shell(self, cli: Cli, config: Config, project_name: Option<&str>) -> Result<()> {
+        let project_path = self.0.path();
+
+        let marker_file = if config.keep_by_default {
+            project_path.join("TO_PRESERVE")
+        } else {
+            project_path.join("TO_DELETE")
+        };
+        if !cli.keep {
+            write(
+                &marker_file,
+                if config.keep_by_default {
+                    "Delete this file if you want to delete this project"
+                } else {
+                    "Delete this file if you want to preserve this project"
+                },
+            )?;
+        }
+
+        if let Some(path) = cargo_target_dir(&cli, &config, project_path)? {
+            env::set_var("CARGO_TARGET_DIR", path);
+        }
+
+        let name = project_name
+            .map(Cow::Borrowed)
+            .unwrap_or_else(|| project_path.file_name().unwrap().to_string_lossy());
+
+        let mut subprocesses = start_subprocesses(&config, project_path, &name);
+
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            kill_subprocesses(&mut subprocesses)?;
+            self.discard(&cli);
+            bail!("interrupted");
+        }
+        let check = if config.background_check {
+            BackgroundCheck::spawn(project_path, cli.locked)
+                .inspect_err(|err| log::error!("cannot start cargo check: {err}"))
+                .ok()
+        } else {
+            None
+        };
+
+        if cli.format == Format::Human {
+            log::info!("Temporary project created at: {}", project_path.display());
+        }
+
+        if config.welcome_message && cli.format == Format::Human {
+            match config.welcome_message_text.as_deref() {
+                Some(text) => println!("\n{text}"),
+                None if cli.keep => println!(
+                    "\nThe project will be preserved when exiting the shell.\nTo exit the project, \
+                    you can type \"exit\" or use `Ctrl+D`"
+                ),
+                None if config.keep_by_default => println!(
+                    "\nTo delete the project when exiting the shell, delete the `TO_PRESERVE` \
+                    file.\nTo exit the project, you can type \"exit\" or use `Ctrl+D`"
+                ),
+                None => println!(
+                    "\nTo preserve the project when exiting the shell, don't forget to delete the \
+            `TO_DELETE` file.\nTo exit the project, you can type \"exit\" or use `Ctrl+D`"
+                ),
+            }
+        }
+
+        let res = {
+            let (mut shell_process, _is_editor) =
+                shell_command(&cli, &config, &name, project_path)?;
 
+            let program = shell_process.get_program().to_owned();
             let res = shell_process.current_dir(project_path).spawn();
 
             #[cfg(windows)]
-            if config.editor.is_some() {
+            if _is_editor {
                 unsafe {
                     crate::binding::FreeConsole();
                 }
             }
 
-            if let Ok(mut child) = res {
-                child.wait().context("cannot wait shell process")
-            } else {
-                bail!("cannot spawn shell process")
+            match res {
+                Ok(mut child) => child.wait().context("cannot wait shell process"),
+                Err(err) => bail!(
+                    "cannot spawn shell process `{}`: {err}",
+                    program.to_string_lossy()
+                ),
             }
         };
 
-        project.clean_up(
-            &delete_file,
-            cli.worktree_branch.flatten().as_deref(),
-            cli.project_name.as_deref(),
-            config.preserved_project_dir.as_deref(),
+        // `Ctrl+C` in the shell was meant for its programs, only the one at the prompt counts.
+        INTERRUPTED.store(false, Ordering::SeqCst);
+
+        // The project may be deleted by the clean up.
+        let project_path = project_path.to_path_buf();
+        let crate_name = crate_name(&project_path);
+
+        let preserved_path = self.clean_up(
+            &cli,
+            &config,
+            &marker_file,
+            project_name,
             &mut subprocesses,
-            config.prompt,
+            check,
         )?;
 
+        if cli.format == Format::Json {
+            let preserved = preserved_path.is_some();
+            println!(
+                "{}",
+                json_report(
+                    &preserved_path.unwrap_or(project_path),
+                    crate_name,
+                    &cli,
+                    &config,
+                    preserved,
+                )?
+            );
+        }
+
         ensure!(res.is_ok(), "problem within the shell process");
 
         Ok(())
     }
 
-    fn temporary(
-        cli: Cli,
-        temporary_project_dir: &Path,
-        git_repo_depth: Option<&Depth>,
-        vcs: Option<&str>,
-    ) -> Result<Self> {
-        let tmp_dir = {
-            let mut builder = tempfile::Builder::new();
-            let mut suffix = String::new();
+    fn temporary(cli: Cli, config: &Config, project_name: Option<&str>) -> Result<Self> {
+        let temporary_project_dir = config.temporary_project_dir.as_path();
 
-            let prefix = if cli.worktree_branch.is_some() {
-                "wk-"
-            } else {
-                "tmp-"
-            };
+        let current_dir = env::current_dir().unwrap_or_default();
+        if let Err(err) = check_temporary_project_dir(temporary_project_dir, &current_dir) {
+            match config.temporary_project_dir_severity {
+                Severity::Warn => log::warn!("{err}"),
+                Severity::Error => bail!(
+                    "{err}, change the `temporary_project_dir` setting to avoid mixing the \
+                    projects with your files"
+                ),
+            }
+        }
 
-            if let Some(name) = cli.project_name.as_deref() {
-                suffix = format!("-{name}");
-            };
+        let tmp_dir = {
+            let (prefix, suffix) = affixes(&cli, config, project_name);
 
-            if !temporary_project_dir.exists() {
-                create_dir_all(temporary_project_dir)
-                    .context("cannot create temporary project's directory")?;
-            }
+            create_temporary_project_dir(temporary_project_dir)?;
 
-            builder
-                .prefix(&prefix)
+            tempfile::Builder::new()
+                .prefix(prefix)
                 .suffix(&suffix)
                 .tempdir_in(temporary_project_dir)?
         };
 
-        let tmp_dir_path = tmp_dir.path();
+        let project = Project(tmp_dir);
 
-        let project_name = cli.project_name.unwrap_or_else(|| {
-            tmp_dir_path
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .to_lowercase()
-        });
+        match project.setup(&cli, config) {
+            Ok(()) => Ok(project),
+            Err(err) => {
+                project.discard(&cli);
+                Err(err)
+            }
+        }
+    }
 
-        if let Some(maybe_branch) = cli.worktree_branch.as_ref() {
-            let mut command = std::process::Command::new("git");
-            command.args(["worktree", "add"]);
+    /// Create the project in the temporary directory.
+    fn setup(&self, cli: &Cli, config: &Config) -> Result<()> {
+        let tmp_dir_path = self.0.path();
 
-            match maybe_branch {
-                Some(branch) => command.arg(tmp_dir_path).arg(branch),
-                None => command.arg("-d").arg(tmp_dir_path),
-            };
+        if let Some(url) = cli.git.as_deref() {
+            ensure!(
+                !is_offline() || is_local_repository(url),
+                "cannot clone `{url}` while offline (`--offline`, the `offline` setting or \
+                `CARGO_NET_OFFLINE` is set), only a local repository can be cloned"
+            );
+        }
+
+        let (mut command, error) = setup_command(cli, config, tmp_dir_path);
+
+        ensure!(
+            command
+                .status()
+                .with_context(|| format!(
+                    "Could not start {}",
+                    command.get_program().to_string_lossy()
+                ))?
+                .success(),
+            error
+        );
+        ensure_not_interrupted()?;
+
+        if cli.lib_with_bin {
+            let crate_name = crate_name(tmp_dir_path).context("cannot read the crate's name")?;
+            write(
+                tmp_dir_path.join("src").join("main.rs"),
+                lib_with_bin_main(&crate_name),
+            )?;
+        }
 
+        if !cli.sparse.is_empty() {
             ensure!(
-                command.status().context("Could not start git")?.success(),
-                "cannot create working tree"
+                Command::new("git")
+                    .current_dir(tmp_dir_path)
+                    .args(["sparse-checkout", "set"])
+                    .args(&cli.sparse)
+                    .status()
+                    .context("Could not start git")?
+                    .success(),
+                "cannot set the sparse checkout"
             );
-        } else if let Some(url) = &cli.git {
-            let mut command = std::process::Command::new("git");
-            command.arg("clone").arg(url).arg(tmp_dir.as_ref());
-
-            match git_repo_depth {
-                Some(Depth::Active(false)) => {}
-                None | Some(Depth::Active(true)) => {
-                    command.arg("--depth").arg("1");
-                }
-                Some(Depth::Level(level)) => {
-                    command.arg("--depth").arg(level.to_string());
-                }
+        }
+
+        // A repository cloned from a local path or without depth is already complete.
+        let unshallow = (cli.unshallow || config.git_unshallow)
+            && cli.git.is_some()
+            && tmp_dir_path.join(".git").join("shallow").exists();
+
+        if unshallow {
+            ensure!(
+                !is_offline(),
+                "cannot fetch the history of the repository while offline"
+            );
+            ensure!(
+                Command::new("git")
+                    .current_dir(tmp_dir_path)
+                    .args(["fetch", "--unshallow"])
+                    .status()
+                    .context("Could not start git")?
+                    .success(),
+                "cannot fetch the history of the repository"
+            );
+            ensure_not_interrupted()?;
+        }
+
+        if let Some(rev) = cli.git_rev.as_deref() {
+            let shallow =
+                !unshallow && !matches!(config.git_repo_depth, Some(Depth::Active(false)));
+            checkout_rev(tmp_dir_path, rev, shallow)?;
+            ensure_not_interrupted()?;
+        }
+
+        if let Some(branch) = cli.new_branch.as_deref() {
+            ensure!(
+                Command::new("git")
+                    .current_dir(tmp_dir_path)
+                    .args(["checkout", "-b", branch])
+                    .status()
+                    .context("Could not start git")?
+                    .success(),
+                "cannot create branch `{branch}`"
+            );
+        }
+
+        for patch in &cli.apply {
+            let patch_path = std::path::absolute(patch)?;
+            ensure!(
+                patch_path.is_file(),
+                "cannot find patch `{}`",
+                patch.display()
+            );
+
+            let mut command = if tmp_dir_path.join(".git").exists() {
+                let mut command = Command::new("git");
+                command.arg("apply").arg(&patch_path);
+                command
+            } else {
+                let mut command = Command::new("patch");
+                command.arg("-p1").arg("-i").arg(&patch_path);
+                command
             };
 
+            // The standard output is reserved to the path of `--no-shell` and to the JSON report.
             ensure!(
-                command.status().context("Could not start git")?.success(),
-                "cannot clone repository"
+                command
+                    .current_dir(tmp_dir_path)
+                    .stdout(std::io::stderr())
+                    .status()
+                    .with_context(|| format!(
+                        "Could not start {}",
+                        command.get_program().to_string_lossy()
+                    ))?
+                    .success(),
+                "cannot apply patch `{}`",
+                patch.display()
             );
-        } else {
-            let mut command = std::process::Command::new("cargo");
-            command
-                .current_dir(&tmp_dir)
-                .args(["init", "--name", project_name.as_str()]);
+        }
 
-            if cli.lib {
-                command.arg("--lib");
+        for name in cli.scaffold.iter() {
+            for file in &config.scaffolds[name] {
+                let path = tmp_dir_path.join(&file.path);
+                if let Some(parent) = path.parent() {
+                    create_dir_all(parent)?;
+                }
+                write(&path, &file.content)
+                    .with_context(|| format!("cannot write {}", file.path.display()))?;
             }
+        }
 
-            if let Some(arg) = vcs {
-                command.args(["--vcs", arg]);
-            }
+        // A clone or a worktree is the user's code, the script would show up as an untracked file.
+        if config.generate_keep_script
+            && !config.keep_by_default
+            && !cli.keep
+            && !cli.no_shell
+            && cli.git.is_none()
+            && !cli.worktree()
+        {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
 
-            if let Some(num) = &cli.edition {
-                match num {
-                    15 | 2015 => {
-                        command.args(["--edition", "2015"]);
-                    }
-                    18 | 2018 => {
-                        command.args(["--edition", "2018"]);
-                    }
-                    21 | 2021 => {
-                        command.args(["--edition", "2021"]);
-                    }
-                    _ => log::error!("cannot find the {} edition, using the latest", num),
-                }
+                let script = tmp_dir_path.join("keep");
+                write(
+                    &script,
+                    "#!/bin/sh\nrm -f \"$(dirname \"$0\")/TO_DELETE\"\n\
+                    echo \"The project will be preserved when exiting the shell\"\n",
+                )?;
+                std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
             }
 
-            ensure!(
-                command.status().context("Could not start cargo")?.success(),
-                "cargo command failed"
-            );
+            #[cfg(windows)]
+            write(
+                tmp_dir_path.join("keep.cmd"),
+                "@del \"%~dp0TO_DELETE\" 2>nul\r\n\
+                @echo The project will be preserved when exiting the shell\r\n",
+            )?;
         }
 
-        if !cli.dependencies.is_empty() {
-            let mut toml = OpenOptions::new()
-                .append(true)
-                .open(tmp_dir_path.join("Cargo.toml"))?;
+        if let Some(path) = cli.reuse_lock.as_deref() {
+            let lock = std::fs::read_to_string(path)
+                .with_context(|| format!("cannot read lockfile `{}`", path.display()))?;
+            lock.parse::<toml::Table>()
+                .with_context(|| format!("cannot parse lockfile `{}`", path.display()))?;
+            write(tmp_dir_path.join("Cargo.lock"), lock)?;
+        }
 
-            for dependency in cli.dependencies.iter() {
-                writeln!(toml, "{}", format_dependency(dependency))?
+        if let Some(script) = cli.script.as_deref() {
+            std::fs::copy(script, tmp_dir_path.join("src").join("main.rs"))
+                .with_context(|| format!("cannot copy {}", script.display()))?;
+        }
+
+        if let Some(path) = cli.copy.as_deref() {
+            if path.is_dir() {
+                copy_dir(path, tmp_dir_path)?;
+            } else {
+                let file = if cli.lib || cli.lib_with_bin {
+                    "lib.rs"
+                } else {
+                    "main.rs"
+                };
+                std::fs::copy(path, tmp_dir_path.join("src").join(file))
+                    .with_context(|| format!("cannot copy {}", path.display()))?;
             }
         }
 
-        if let Some(maybe_bench_name) = cli.bench {
-            let bench_name = maybe_bench_name.unwrap_or("benchmark".to_string());
+        if let Some(code) = cli.code.as_deref() {
+            let code = if code.contains("fn main") {
+                format!("{code}\n")
+            } else {
+                let body = code
+                    .lines()
+                    .map(|line| format!("    {line}\n"))
+                    .collect::<String>();
+                format!("fn main() {{\n{body}}}\n")
+            };
+
+            write(tmp_dir_path.join("src").join("main.rs"), code)?;
+        }
 
-            let mut toml = OpenOptions::new()
-                .append(true)
-                .open(tmp_dir_path.join("Cargo.toml"))?;
+        add_dependencies_to_project(
+            tmp_dir_path,
+            &cli.dependencies,
+            cli.verbose_deps,
+            config.use_cargo_add,
+            config.manifest_wrap_width.unwrap_or(DEFAULT_WRAP_WIDTH),
+        )?;
 
-            writeln!(
-                toml,
-                "[dev-dependencies]\ncriterion = \"*\"\n\n[profile.release]\ndebug = true\n\n
-                [[bench]]\nname = \"{bench_name}\"\nharness = false",
+        // A cloned repository already has its own manifest.
+        if let Some(extra_manifest) = config
+            .extra_manifest
+            .as_deref()
+            .filter(|_| cli.git.is_none() && !cli.worktree())
+        {
+            let manifest_path = tmp_dir_path.join("Cargo.toml");
+            write(
+                &manifest_path,
+                merge_manifest(&std::fs::read_to_string(&manifest_path)?, extra_manifest)?,
             )?;
+        }
 
-            let bench_folder = tmp_dir_path.join("benches");
-            create_dir_all(&bench_folder)?;
-            let mut bench_file = bench_folder.join(bench_name);
-            bench_file.set_extension("rs");
+        if cli.build_script {
+            let has_build_dependencies = cli
+                .dependencies
+                .iter()
+                .any(|x| x.kind() == DependencyKind::Build);
 
             write(
-                bench_file,
-                "use criterion::{black_box, criterion_group, criterion_main, Criterion};\n\n\
-        fn criterion_benchmark(_c: &mut Criterion) {\n\tprintln!(\"Hello, world!\");\n}\n\n\
-        criterion_group!(\n\tbenches,\n\tcriterion_benchmark\n);\ncriterion_main!(benches);",
+                tmp_dir_path.join("build.rs"),
+                build_script(has_build_dependencies),
             )?;
         }
 
-        Ok(Project(tmp_dir))
-    }
+        if let Some(preset) = cli.lints.as_deref() {
+            let manifest_path = tmp_dir_path.join("Cargo.toml");
+            let manifest = std::fs::read_to_string(&manifest_path)?;
+            write(
+                manifest_path,
+                add_lints(&manifest, &lints_preset(config, preset)?)?,
+            )?;
+        }
 
-    fn clean_up(
-        self,
-        delete_file: &Path,
-        worktree_branch: Option<&str>,
-        project_name: Option<&str>,
-        preserved_project_dir: Option<&Path>,
-        subprocesses: &mut [Child],
-        prompt: bool,
-    ) -> Result<()> {
-        let delete = if !delete_file.exists() {
-            false
-        } else if prompt {
-            println!("Are you sure you want to delete this project? (Y/n)");
-
-            let mut input = String::new();
-
-            loop {
-                match stdin().read_line(&mut input) {
-                    Ok(_n) => match input.trim() {
-                        "" | "Yes" | "yes" | "Y" | "y" => {
-                            break true;
-                        }
-                        "No" | "no" | "N" | "n" => {
-                            break false;
-                        }
-                        _ => println!("hmm, `{}` doesn't look like `yes` or `no`", input.trim()),
-                    },
-                    Err(err) => {
-                        log::error!("failed to read input: {}", err);
+        if let Some(description) = cli.description.as_deref() {
+            let manifest_path = tmp_dir_path.join("Cargo.toml");
+            let manifest = std::fs::read_to_string(&manifest_path)?;
+            write(manifest_path, set_description(&manifest, description)?)?;
+
+            let readme_path = tmp_dir_path.join("README.md");
+            if cli.replace_readme || !readme_path.exists() {
+                let title = crate_name(tmp_dir_path).unwrap_or_default();
+                write(readme_path, format!("# {title}\n\n{description}\n"))
+                    .context("cannot write README.md")?;
+            } else {
+                log::warn!("README.md already exists, use `--replace-readme` to replace it");
+            }
+        }
+
+        if let Some(bench_names) = cli.bench.as_ref() {
+            let default_name = ["benchmark".to_string()];
+            let bench_names = if bench_names.is_empty() {
+                &default_name[..]
+            } else {
+                &bench_names[..]
+            };
+
+            let manifest_path = tmp_dir_path.join("Cargo.toml");
+            let manifest = std::fs::read_to_string(&manifest_path)?;
+            write(
+                &manifest_path,
+                set_profile(&manifest, "release", &[("debug", true.into())])?,
+            )?;
+
+            let mut toml = OpenOptions::new().append(true).open(&manifest_path)?;
+
+            let bench_folder = tmp_dir_path.join("benches");
+            create_dir_all(&bench_folder)?;
+
+            // The default body doesn't use the `Criterion` argument.
+            let criterion = if config.bench_fn_body.is_some() {
+                "c"
+            } else {
+                "_c"
+            };
+            let bench_fn_body = config
+                .bench_fn_body
+                .as_deref()
+                .unwrap_or("println!(\"Hello, world!\");")
+                .lines()
+                .map(|line| format!("\t{line}\n"))
+                .collect::<String>();
+
+            for bench_name in bench_names {
+                writeln!(
+                    toml,
+                    "\n[[bench]]\nname = \"{bench_name}\"\nharness = false",
+                )?;
+
+                let mut bench_file = bench_folder.join(bench_name);
+                bench_file.set_extension("rs");
+
+                write(
+                    bench_file,
+                    format!(
+                        "use criterion::{{black_box, criterion_group, criterion_main, Criterion}};\n\n\
+            fn criterion_benchmark({criterion}: &mut Criterion) {{\n{bench_fn_body}}}\n\n\
+            criterion_group!(\n\tbenches,\n\tcriterion_benchmark\n);\ncriterion_main!(benches);"
+                    ),
+                )?;
+            }
+        }
+
+        if let Some(panic_crate) = &cli.no_std {
+            if cli.lib {
+                let lib_file = tmp_dir_path.join("src").join("lib.rs");
+                let lib = std::fs::read_to_string(&lib_file)?;
+                write(lib_file, format!("#![no_std]\n\n{lib}"))?;
+            } else {
+                let panic_handler = match panic_crate {
+                    Some(panic_crate) => {
+                        format!("use {} as _;\n", panic_crate.key().replace('-', "_"))
                     }
-                }
+                    None => "use core::panic::PanicInfo;\n\n\
+                        #[panic_handler]\n\
+                        fn panic(_info: &PanicInfo) -> ! {\n    loop {}\n}\n"
+                        .to_string(),
+                };
 
-                input.clear()
+                write(
+                    tmp_dir_path.join("src").join("main.rs"),
+                    format!("#![no_std]\n#![no_main]\n\n{panic_handler}"),
+                )?;
+
+                let manifest_path = tmp_dir_path.join("Cargo.toml");
+                let mut manifest = std::fs::read_to_string(&manifest_path)?;
+                for profile in ["dev", "release"] {
+                    manifest = set_profile(&manifest, profile, &[("panic", "abort".into())])?;
+                }
+                write(&manifest_path, manifest)?;
             }
-        } else {
-            true
-        };
+        }
+
+        if let Some(target) = cli.target.as_deref() {
+            let cargo_config_dir = tmp_dir_path.join(".cargo");
+            create_dir_all(&cargo_config_dir)?;
+            write(
+                cargo_config_dir.join("config.toml"),
+                format!("[build]\ntarget = {target:?}\n"),
+            )?;
+        }
+
+        if let Some(channel) = cli.toolchain.as_deref() {
+            write(
+                tmp_dir_path.join("rust-toolchain.toml"),
+                format!("[toolchain]\nchannel = {channel:?}\n"),
+            )?;
+        }
+
+        if let Some(post_create) = config.post_create.as_deref() {
+            // The output of the script must not mix with the path of `--no-shell` or the JSON
+            // report.
+            let mut command = post_create_command(post_create);
+            command.current_dir(tmp_dir_path).stdout(std::io::stderr());
+
+            let success = command
+                .status()
+                .context("Could not start the post_create command")?
+                .success();
 
-        if !delete {
-            let _ = remove_file(delete_file);
-            let tmp_dir = self.preserve_dir(project_name, preserved_project_dir)?;
+            if config.post_create_required {
+                ensure!(success, "the post_create command failed");
+            } else if !success {
+                log::error!("the post_create command failed");
+            }
+            ensure_not_interrupted()?;
+        }
 
-            log::info!("Project directory_preserved_at: {}", tmp_dir.display());
-        } else if worktree_branch.is_some() {
-            let mut command = std::process::Command::new("git");
+        if cli.prefetch && is_offline() {
+            log::warn!("cannot fetch dependencies while offline, skipping `--fetch`");
+        } else if cli.prefetch {
+            let mut command = Command::new("cargo");
             command
-                .args(["worktree", "remove"])
-                .arg(self.0.path())
-                .arg("--force");
+                .current_dir(tmp_dir_path)
+                .arg("fetch")
+                .args(cli.locked.then_some("--locked"));
 
             ensure!(
-                command.status().context("Could not start git")?.success(),
-                "cannot remove working tree"
+                command.status().context("Could not start cargo")?.success(),
+                "cannot fetch dependencies"
             );
         }
 
-        kill_subprocesses(subprocesses)
-    }
-
-    fn preserve_dir(
-        self,
-        project_name: Option<&str>,
-        preserved_project_dir: Option<&Path>,
-    ) -> Result<PathBuf> {
-        let tmp_dir = self.0.into_path();
+        if cli.build || config.always_build {
+            let mut command = Command::new("cargo");
+            command
+                .current_dir(tmp_dir_path)
+                .arg("build")
+                .args(cli.release.then_some("--release"))
+                .args(cli.locked.then_some("--locked"));
 
-        let mut final_dir = if let Some(preserved_project_dir) = preserved_project_dir {
-            if !preserved_project_dir.exists() {
-                create_dir_all(preserved_project_dir)
-                    .context("cannot create preserve project's directory")?;
+            if let Some(path) = cargo_target_dir(cli, config, tmp_dir_path)? {
+                command.env("CARGO_TARGET_DIR", path);
             }
 
-            preserved_project_dir.join(
-                tmp_dir
-                    .file_name()
-                    .context("cannot create preserve project's directory")?,
-            )
-        } else {
-            tmp_dir.clone()
-        };
-
-        if let Some(name) = project_name {
-            final_dir = final_dir.with_file_name(name);
+            match command.status() {
+                Ok(status) if status.success() => {}
+                Ok(_) => log::error!("cannot build the project, starting the shell anyway"),
+                Err(err) => log::error!("could not start cargo: {err}"),
+            }
+        }
+
+        if cli.snapshot {
+            if let Err(err) = Snapshot::take(tmp_dir_path).and_then(|x| x.save(tmp_dir_path)) {
+                log::error!("cannot take a snapshot of the project: {err}");
+            }
+        }
+
+        ensure_not_interrupted()
+    }
+
+    /// Delete a project that failed to be created or has been interrupted.
+    fn discard(self, cli: &Cli) {
+        drop(self.0);
+
+        // The working tree has been deleted with the directory.
+        if cli.worktree() {
+            let _ = worktree_git_command(cli)
+                .args(["worktree", "prune"])
+                .status();
+        }
+    }
+
+    fn dry_run(cli: &Cli, config: &Config, project_name: Option<&str>) -> Result<()> {
+        let (prefix, suffix) = affixes(cli, config, project_name);
+        let tmp_dir_path = config
+            .temporary_project_dir
+            .join(format!("{prefix}XXXXXX{suffix}"));
+        let (command, _) = setup_command(cli, config, &tmp_dir_path);
+
+        println!("Temporary project: {}", tmp_dir_path.display());
+        println!("Command: {command:?}");
+
+        if let Some(script) = cli.script.as_deref() {
+            println!("Script: {}", script.display());
+        }
+
+        if cli.prefetch && !is_offline() {
+            let mut command = Command::new("cargo");
+            command.arg("fetch").args(cli.locked.then_some("--locked"));
+            println!("Command: {command:?}");
+        }
+
+        if cli.build || config.always_build {
+            let mut command = Command::new("cargo");
+            command
+                .arg("build")
+                .args(cli.release.then_some("--release"))
+                .args(cli.locked.then_some("--locked"));
+            println!("Command: {command:?}");
+        }
+
+        if cli.run {
+            let mut command = Command::new("cargo");
+            command
+                .arg("run")
+                .args(cli.release.then_some("--release"))
+                .args(cli.locked.then_some("--locked"))
+                .args(&cli.run_args);
+            println!("Command: {command:?}");
+        }
+
+        if config.use_cargo_add {
+            for dependency in cli.dependencies.iter() {
+                println!(
+                    "Command: {:?}",
+                    cargo_add_command(&tmp_dir_path, dependency)
+                );
+            }
+        } else if !cli.dependencies.is_empty() {
+            println!("Dependencies:");
+            write_dependencies(
+                &mut std::io::stdout(),
+                &cli.dependencies,
+                config.manifest_wrap_width.unwrap_or(DEFAULT_WRAP_WIDTH),
+            )?;
+        }
+
+        if !config.subprocesses.is_empty() && !cli.no_shell {
+            println!("Subprocesses:");
+            for subprocess in config.subprocesses.iter() {
+                println!(
+                    "{} ({})",
+                    subprocess.command,
+                    if subprocess.foreground {
+                        "foreground"
+                    } else {
+                        "background"
+                    }
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn clean_up(
+        self,
+        cli: &Cli,
+        config: &Config,
+        marker_file: &Path,
+        project_name: Option<&str>,
+        subprocesses: &mut [Child],
+        check: Option<BackgroundCheck>,
+    ) -> Result<Option<PathBuf>> {
+        if let Some(check) = check {
+            check.report();
+        }
+
+        if cli.snapshot && cli.format == Format::Human {
+            let project_path = self.0.path();
+
+            match Snapshot::load(project_path)
+                .and_then(|snapshot| Ok(snapshot.diff(&Snapshot::take(project_path)?)))
+            {
+                Ok(changes) if changes.is_empty() => {
+                    log::info!("No changes since the project was created")
+                }
+                Ok(changes) => log::info!(
+                    "Changes since the project was created:\n{}",
+                    changes.to_string().trim_end()
+                ),
+                Err(err) => log::error!("cannot compare the project with its snapshot: {err}"),
+            }
+        }
+
+        // With `keep_by_default`, the project is deleted when the marker file
+        // has been removed.
+        let marked_for_deletion = if config.keep_by_default {
+            !cli.keep && !marker_file.exists()
+        } else {
+            marker_file.exists()
+        };
+
+        let delete = if !marked_for_deletion {
+            false
+        } else if config.prompt && !cli.delete {
+            confirm("Are you sure you want to delete this project?")
+        } else {
+            true
+        };
+
+        let archived = match cli.archive.as_deref() {
+            Some(archive) if !delete => {
+                let _ = remove_file(marker_file);
+                self.archive(archive, project_name)?;
+
+                if cli.format == Format::Human {
+                    log::info!("Project archived at: {}", archive.display());
+                }
+                true
+            }
+            _ => false,
+        };
+
+        let mut preserved_path = None;
+
+        if !delete && !archived {
+            let _ = remove_file(marker_file);
+            let tmp_dir = self.preserve_dir(project_name, preserved_project_dir(cli, config))?;
+
+            if cli.format == Format::Human {
+                log::info!("Project directory_preserved_at: {}", tmp_dir.display());
+            }
+            preserved_path = Some(tmp_dir);
+        } else if cli.worktree() {
+            let mut command = worktree_git_command(cli);
+            command
+                .args(["worktree", "remove"])
+                .arg(std::path::absolute(self.0.path())?)
+                .arg("--force");
+
+            ensure!(
+                command.status().context("Could not start git")?.success(),
+                "cannot remove working tree"
+            );
+
+            if let Some(branch) = cli.worktree_new.as_deref() {
+                // Without the prompt, the branch is only deleted with `--delete`.
+                let delete_branch = cli.delete
+                    || (config.prompt
+                        && confirm(&format!("Do you want to delete the branch `{branch}`?")));
+
+                if !delete_branch {
+                    log::info!("The branch `{branch}` is kept, use `--delete` to delete it");
+                } else {
+                    let mut command = worktree_git_command(cli);
+                    command.args(["branch", "-D", branch]);
+
+                    ensure!(
+                        command.status().context("Could not start git")?.success(),
+                        "cannot delete branch `{branch}`"
+                    );
+                }
+            }
+        }
+
+        kill_subprocesses(subprocesses)?;
+
+        Ok(preserved_path)
+    }
+
+    /// Write a gzipped tarball of the project, without its `target` directory.
+    fn archive(&self, archive: &Path, project_name: Option<&str>) -> Result<()> {
+        fn append_dir(
+            builder: &mut tar::Builder<GzEncoder<File>>,
+            dir: &Path,
+            archive_dir: &Path,
+            is_root: bool,
+        ) -> Result<()> {
+            builder.append_dir(archive_dir, dir)?;
+
+            for entry in read_dir(dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                let archive_path = archive_dir.join(entry.file_name());
+
+                if !entry.file_type()?.is_dir() {
+                    builder.append_path_with_name(&path, &archive_path)?;
+                } else if !is_root || entry.file_name() != "target" {
+                    append_dir(builder, &path, &archive_path, false)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        let project_path = self.0.path();
+        let archive_dir = project_name
+            .map(OsStr::new)
+            .or(project_path.file_name())
+            .context("cannot get project's directory name")?;
+
+        let file = File::create(archive).context("cannot create archive")?;
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        builder.follow_symlinks(false);
+        append_dir(&mut builder, project_path, Path::new(archive_dir), true)?;
+        builder.into_inner()?.finish()?;
+
+        Ok(())
+    }
+
+    /// Print the projects of the temporary and preserved project directories.
+    pub fn list(config: &Config) -> Result<()> {
+        let mut dirs = vec![config.temporary_project_dir.as_path()];
+        if let Some(preserved_project_dir) = config.preserved_project_dir.as_deref() {
+            if preserved_project_dir != config.temporary_project_dir {
+                dirs.push(preserved_project_dir);
+            }
+        }
+
+        let mut rows = Vec::new();
+        for dir in dirs {
+            let Ok(entries) = read_dir(dir) else {
+                continue;
+            };
+
+            for path in entries.flatten().map(|entry| entry.path()) {
+                let manifest_path = path.join("Cargo.toml");
+                if !path.is_dir() || !manifest_path.exists() || !is_project(&path, config) {
+                    continue;
+                }
+
+                let age = path
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| modified.elapsed().ok());
+                let status = if path.join("TO_DELETE").exists() || path.join("TO_PRESERVE").exists()
+                {
+                    "temporary"
+                } else {
+                    "preserved"
+                };
+                let dependencies = match std::fs::read_to_string(&manifest_path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|manifest| count_manifest_dependencies(&manifest))
+                {
+                    Ok(count) => count.to_string(),
+                    Err(err) => {
+                        log::debug!("cannot read {}: {err}", manifest_path.display());
+                        "?".to_string()
+                    }
+                };
+
+                rows.push((
+                    path.file_name().unwrap().to_string_lossy().to_string(),
+                    age,
+                    status,
+                    dependencies,
+                ));
+            }
+        }
+
+        if rows.is_empty() {
+            println!("No project found");
+            return Ok(());
+        }
+
+        // Newest first.
+        rows.sort_by_key(|(_, age, _, _)| *age);
+
+        let width = rows
+            .iter()
+            .map(|(name, _, _, _)| name.len())
+            .max()
+            .unwrap_or_default()
+            .max("NAME".len());
+
+        println!("{:<width$}  {:>5}  {:<9}  DEPS", "NAME", "AGE", "STATUS");
+        for (name, age, status, dependencies) in rows {
+            let age = age.map(format_age).unwrap_or_else(|| "?".to_string());
+            println!("{name:<width$}  {age:>5}  {status:<9}  {dependencies:>4}");
+        }
+
+        Ok(())
+    }
+
+    fn preserve_dir(
+        self,
+        project_name: Option<&str>,
+        preserved_project_dir: Option<&Path>,
+    ) -> Result<PathBuf> {
+        let tmp_dir = self.0.into_path();
+
+        let mut final_dir = if let Some(preserved_project_dir) = preserved_project_dir {
+            if !preserved_project_dir.exists() {
+                create_dir_all(preserved_project_dir)
+                    .context("cannot create preserve project's directory")?;
+            }
+
+            preserved_project_dir.join(
+                tmp_dir
+                    .file_name()
+                    .context("cannot create preserve project's directory")?,
+            )
+        } else {
+            tmp_dir.clone()
+        };
+
+        if let Some(name) = project_name {
+            final_dir = final_dir.with_file_name(name);
         }
 
         if final_dir != tmp_dir {
@@ -337,3 +1186,1736 @@ impl Project {
         Ok(final_dir)
     }
 }
+
+/// Returns `true` if the path is relative and doesn't go up, a scaffold can't write outside of the
+/// project.
+fn is_inside_project(path: &Path) -> bool {
+    use std::path::Component;
+
+    path.components().any(|x| matches!(x, Component::Normal(_)))
+        && path
+            .components()
+            .all(|x| matches!(x, Component::Normal(_) | Component::CurDir))
+}
+
+/// Returns the age in the largest unit, e.g. `3d` or `12m`.
+fn format_age(age: std::time::Duration) -> String {
+    let secs = age.as_secs();
+
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Returns the command started in the project and whether it's an editor.
+///
+/// The options of the command line win over the config file: `--editor`, then `--open`, then the
+/// editor of the environment with `use_env_editor`, then the `editor` and `terminal_command`
+/// settings. Without any of them, the shell is started.
+fn shell_command(
+    cli: &Cli,
+    config: &Config,
+    name: &str,
+    project_path: &Path,
+) -> Result<(Command, bool)> {
+    let env_editor = env_editor(cli, config);
+
+    let (editor, editor_args) = if cli.no_editor || cli.open.is_some() {
+        (None, &cli.editor_args)
+    } else if let Some(editor) = cli.editor.as_deref() {
+        (Some(editor), &cli.editor_args)
+    } else if let Some((editor, editor_args)) = env_editor.as_ref() {
+        (Some(editor.as_str()), editor_args)
+    } else if !cli.editor_args.is_empty() {
+        (config.editor.as_deref(), &cli.editor_args)
+    } else {
+        (
+            config.editor.as_deref(),
+            config.editor_args.as_ref().unwrap_or(&cli.editor_args),
+        )
+    };
+
+    let terminal_command = cli.open.as_deref().or(config.terminal_command.as_deref());
+
+    let mut command = match (editor, terminal_command) {
+        (None, Some(terminal_command)) => {
+            let args = expand_terminal_command(terminal_command, name, project_path);
+
+            let Some((program, args)) = args.split_first() else {
+                bail!("the terminal command is empty");
+            };
+
+            let mut terminal_process = Command::new(program);
+            terminal_process.args(args);
+            terminal_process
+        }
+        (None, None) => {
+            let shell = get_shell(cli.shell.as_deref().or(config.shell.as_deref()));
+            let mut shell_process = Command::new(&shell);
+
+            if config.login_shell {
+                match login_shell_flag(&shell) {
+                    Some(flag) => {
+                        shell_process.arg(flag);
+                    }
+                    None => {
+                        log::warn!("cannot start `{shell}` as a login shell, unknown shell")
+                    }
+                }
+            }
+
+            shell_process
+        }
+        (Some(editor), _) => {
+            let mut ide_process = Command::new(editor);
+            ide_process.args(editor_args).arg(project_path);
+            ide_process
+        }
+    };
+
+    if cli.clean_env || config.clean_env {
+        clean_env(&mut command, config.clean_env_path.as_deref());
+    }
+
+    Ok((command, editor.is_some()))
+}
+
+/// Returns the shell to start in the project, falling back on the user's shell.
+fn get_shell(shell: Option<&str>) -> String {
+    if let Some(shell) = shell {
+        return shell.to_string();
+    }
+
+    #[cfg(unix)]
+    let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+
+    #[cfg(windows)]
+    let shell = env::var("COMSPEC").unwrap_or_else(|_| "cmd".to_string());
+
+    shell
+}
+
+/// Returns the editor of `$VISUAL` or `$EDITOR` and its arguments when the `use_env_editor`
+/// setting is enabled and no editor or terminal command is configured.
+fn env_editor(cli: &Cli, config: &Config) -> Option<(String, Vec<String>)> {
+    if !config.use_env_editor
+        || config.editor.is_some()
+        || config.terminal_command.is_some()
+        || cli.open.is_some()
+    {
+        return None;
+    }
+
+    let command = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|name| env::var(name).ok().filter(|x| !x.trim().is_empty()))?;
+
+    split_editor_command(&command, &cli.editor_args)
+}
+
+/// Splits an editor command like `code --wait` on whitespace, the other arguments are added after
+/// the ones of the command.
+fn split_editor_command(command: &str, args: &[String]) -> Option<(String, Vec<String>)> {
+    let mut words = command.split_whitespace().map(str::to_string);
+    let editor = words.next()?;
+
+    Some((editor, words.chain(args.iter().cloned()).collect()))
+}
+
+/// Environment variables kept by `--clean-env`, along with `PATH`.
+#[cfg(unix)]
+const CLEAN_ENV_VARS: &[&str] = &[
+    "HOME", "USER", "LOGNAME", "SHELL", "TERM", "LANG", "LC_ALL", "TMPDIR",
+];
+
+/// Environment variables kept by `--clean-env`, along with `PATH`.
+#[cfg(windows)]
+const CLEAN_ENV_VARS: &[&str] = &[
+    "SYSTEMROOT",
+    "SYSTEMDRIVE",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERNAME",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "TEMP",
+    "TMP",
+];
+
+/// Environment variables of the toolchain kept by `--clean-env`.
+const CLEAN_ENV_TOOLCHAIN_VARS: &[&str] = &[
+    "CARGO_HOME",
+    "CARGO_TARGET_DIR",
+    "CARGO_NET_OFFLINE",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+];
+
+/// Clear the environment of the command except for the essential variables.
+fn clean_env(command: &mut Command, path: Option<&str>) {
+    let vars = CLEAN_ENV_VARS
+        .iter()
+        .chain(CLEAN_ENV_TOOLCHAIN_VARS)
+        .filter_map(|name| Some((name, env::var_os(name)?)))
+        .collect::<Vec<_>>();
+
+    command
+        .env_clear()
+        .envs(vars)
+        .env("PATH", path.map(OsString::from).unwrap_or_else(clean_path));
+}
+
+/// Returns the directories of cargo and rustc followed by the system directories.
+fn clean_path() -> OsString {
+    let mut dirs = ["cargo", "rustc"]
+        .into_iter()
+        .filter_map(find_program)
+        .collect::<Vec<_>>();
+
+    #[cfg(unix)]
+    dirs.extend(["/usr/local/bin", "/usr/bin", "/bin"].map(PathBuf::from));
+
+    #[cfg(windows)]
+    if let Some(root) = env::var_os("SYSTEMROOT").map(PathBuf::from) {
+        dirs.extend([root.join("System32"), root]);
+    }
+
+    let mut unique = Vec::new();
+    for dir in dirs {
+        if !unique.contains(&dir) {
+            unique.push(dir);
+        }
+    }
+
+    env::join_paths(unique).unwrap_or_default()
+}
+
+/// Returns the directory of the `PATH` containing the program.
+fn find_program(program: &str) -> Option<PathBuf> {
+    let file_name = format!("{program}{}", env::consts::EXE_SUFFIX);
+
+    env::split_paths(&env::var_os("PATH")?).find(|dir| dir.join(&file_name).is_file())
+}
+
+/// Returns the flag starting the shell as a login shell, detected from the name of its binary.
+fn login_shell_flag(shell: &str) -> Option<&'static str> {
+    let name = Path::new(shell).file_stem()?.to_str()?;
+
+    match name {
+        "bash" | "zsh" | "sh" | "dash" | "ksh" | "fish" | "nu" => Some("-l"),
+        "pwsh" => Some("-Login"),
+        _ => None,
+    }
+}
+
+/// Splits the terminal command on whitespace and replaces the `{name}` and `{dir}` placeholders.
+///
+/// The name is sanitized to be usable as a session name by terminal multiplexers.
+fn expand_terminal_command(terminal_command: &str, name: &str, dir: &Path) -> Vec<String> {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    let dir = dir.to_string_lossy();
+
+    terminal_command
+        .split_whitespace()
+        .map(|arg| arg.replace("{name}", &name).replace("{dir}", &dir))
+        .collect()
+}
+
+/// Returns the target directory to set in `CARGO_TARGET_DIR`, if any.
+///
+/// `--target-dir` wins over the environment variable, which wins over the `build.target-dir` of
+/// the project's `.cargo/config.toml`, which wins over the `cargo_target_dir` setting.
+fn cargo_target_dir(cli: &Cli, config: &Config, project_path: &Path) -> Result<Option<PathBuf>> {
+    if let Some(path) = &cli.target_dir {
+        // The shell doesn't run in the current directory.
+        Ok(Some(std::path::absolute(path)?))
+    } else if env::var("CARGO_TARGET_DIR").is_err() && !has_cargo_target_dir(project_path) {
+        Ok(config.cargo_target_dir.clone())
+    } else {
+        Ok(None)
+    }
+}
+
+/// Copy the content of a directory into another one, without the `target` and `.git`
+/// directories at its root.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fn copy_entries(from: &Path, to: &Path, is_root: bool) -> Result<()> {
+        create_dir_all(to)?;
+
+        for entry in read_dir(from)? {
+            let entry = entry?;
+            let path = entry.path();
+            let dest = to.join(entry.file_name());
+
+            if !entry.file_type()?.is_dir() {
+                std::fs::copy(&path, &dest)
+                    .with_context(|| format!("cannot copy {}", path.display()))?;
+            } else if !is_root || (entry.file_name() != "target" && entry.file_name() != ".git") {
+                copy_entries(&path, &dest, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    copy_entries(from, to, true)
+}
+
+/// Create the directory of the temporary projects and its missing parents.
+pub fn create_temporary_project_dir(path: &Path) -> Result<()> {
+    create_dir_all(path).with_context(|| {
+        format!(
+            "cannot create the temporary project directory `{}`",
+            path.display()
+        )
+    })
+}
+
+/// Refuse a temporary project directory that is a crate or the current directory.
+fn check_temporary_project_dir(path: &Path, current_dir: &Path) -> Result<()> {
+    ensure!(
+        !path.join("Cargo.toml").exists(),
+        "the temporary project directory `{}` contains a `Cargo.toml`",
+        path.display()
+    );
+
+    let current_dir = current_dir.canonicalize();
+    if let (Ok(path), Ok(current_dir)) = (path.canonicalize(), current_dir) {
+        ensure!(
+            path != current_dir,
+            "the temporary project directory `{}` is the current directory",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the `build.rs` of `--build-script`.
+fn build_script(has_build_dependencies: bool) -> &'static str {
+    if has_build_dependencies {
+        "fn main() {\n    // cc::Build::new().file(\"src/foo.c\").compile(\"foo\");\n}\n"
+    } else {
+        "fn main() {}\n"
+    }
+}
+
+/// Returns the `src/main.rs` of `--lib-with-bin`, importing the items of the library.
+fn lib_with_bin_main(crate_name: &str) -> String {
+    format!(
+        "#[allow(unused_imports)]\nuse {}::*;\n\nfn main() {{\n    println!(\"Hello, world!\");\n}}\n",
+        crate_name.replace('-', "_")
+    )
+}
+
+/// Returns the project's information as JSON for `--format json`.
+///
+/// The dependencies are the resolved ones: the arguments, the default dependencies, the
+/// dependencies files and manifests and the ones of the `extra_manifest` setting.
+fn json_report(
+    path: &Path,
+    crate_name: Option<String>,
+    cli: &Cli,
+    config: &Config,
+    preserved: bool,
+) -> Result<String> {
+    #[derive(serde::Serialize)]
+    struct Report<'a> {
+        path: &'a Path,
+        crate_name: Option<String>,
+        dependencies: Vec<DependencyReport<'a>>,
+        preserved: bool,
+    }
+
+    #[derive(serde::Serialize)]
+    struct DependencyReport<'a> {
+        name: &'a str,
+        rename: Option<&'a str>,
+        kind: &'static str,
+    }
+
+    // A cloned repository doesn't get the `extra_manifest`.
+    let extra_dependencies = match config
+        .extra_manifest
+        .as_deref()
+        .filter(|_| cli.git.is_none() && !cli.worktree())
+    {
+        Some(manifest) if count_manifest_dependencies(manifest)? > 0 => {
+            parse_manifest_dependencies(manifest)
+                .context("cannot parse the dependencies of `extra_manifest`")?
+        }
+        _ => Vec::new(),
+    };
+
+    let report = Report {
+        path,
+        crate_name,
+        dependencies: cli
+            .dependencies
+            .iter()
+            .chain(&extra_dependencies)
+            .map(|dependency| DependencyReport {
+                name: dependency.name(),
+                rename: dependency.rename(),
+                kind: match dependency.kind() {
+                    DependencyKind::Normal => "normal",
+                    DependencyKind::Dev => "dev",
+                    DependencyKind::Build => "build",
+                },
+            })
+            .collect(),
+        preserved,
+    };
+
+    serde_json::to_string(&report).context("cannot serialize the report")
+}
+
+/// Check the options that can't be checked by clap, before creating anything.
+fn validate(cli: &Cli, config: &Config) -> Result<()> {
+    // A `#![no_main]` binary has no entry point for the host's linker.
+    ensure!(
+        cli.no_std.is_none()
+            || cli.lib
+            || cli.target.is_some()
+            || !(cli.build || config.always_build),
+        "a `--no-std` binary can't be built for the host, use `--target` with a `no_std` target \
+        or `--lib`"
+    );
+
+    if let Some(path) = cli.copy.as_deref() {
+        ensure!(
+            path.exists(),
+            "cannot copy `{}`, it doesn't exist",
+            path.display()
+        );
+        ensure!(
+            path.is_dir() || path.extension().is_some_and(|x| x == "rs"),
+            "cannot copy `{}`, `--copy` expects a `.rs` file or a directory",
+            path.display()
+        );
+        ensure!(
+            path.is_dir() || cli.script.is_none(),
+            "cannot copy `{}`, a Rust file is already given as argument",
+            path.display()
+        );
+    }
+
+    for name in cli.scaffold.iter() {
+        let scaffold = config
+            .scaffolds
+            .get(name)
+            .with_context(|| format!("cannot find scaffold `{name}`"))?;
+
+        for file in scaffold {
+            ensure!(
+                is_inside_project(&file.path),
+                "the path `{}` of scaffold `{name}` is outside of the project",
+                file.path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the directory is a project of cargo-temp: its name starts with the prefix of
+/// the temporary projects or of the worktrees, or it's still temporary.
+fn is_project(path: &Path, config: &Config) -> bool {
+    let prefixes = [
+        config.tmp_prefix.as_deref().unwrap_or("tmp-"),
+        config.worktree_prefix.as_deref().unwrap_or("wk-"),
+    ];
+
+    path.file_name().is_some_and(|name| {
+        prefixes
+            .iter()
+            .any(|x| name.to_string_lossy().starts_with(x))
+    }) || path.join("TO_DELETE").exists()
+        || path.join("TO_PRESERVE").exists()
+}
+
+/// Returns the exit code of cargo-temp for the exit code of the binary.
+///
+/// The code is missing if the process has been terminated by a signal. The codes that don't fit in
+/// a byte (on Windows for example) are a failure, `256` would be read as a success otherwise.
+fn exit_code(code: Option<i32>) -> ExitCode {
+    match code {
+        Some(0) => ExitCode::SUCCESS,
+        Some(code @ 1..=255) => ExitCode::from(code as u8),
+        _ => ExitCode::FAILURE,
+    }
+}
+
+/// Returns the directory where the project is preserved, `--preserve-to` wins over the
+/// `preserved_project_dir` setting.
+fn preserved_project_dir<'a>(cli: &'a Cli, config: &'a Config) -> Option<&'a Path> {
+    cli.preserve_to
+        .as_deref()
+        .or(config.preserved_project_dir.as_deref())
+}
+
+/// Returns `true` if the project's cargo configuration sets `build.target-dir`.
+fn has_cargo_target_dir(project_path: &Path) -> bool {
+    ["config.toml", "config"].iter().any(|file| {
+        std::fs::read_to_string(project_path.join(".cargo").join(file))
+            .ok()
+            .and_then(|content| content.parse::<toml::Table>().ok())
+            .and_then(|cargo_config| {
+                cargo_config
+                    .get("build")?
+                    .as_table()?
+                    .get("target-dir")
+                    .cloned()
+            })
+            .is_some()
+    })
+}
+
+/// Returns the prefix and the suffix of the temporary directory's name.
+fn affixes<'a>(cli: &Cli, config: &'a Config, project_name: Option<&str>) -> (&'a str, String) {
+    let prefix = if cli.worktree() {
+        config.worktree_prefix.as_deref().unwrap_or("wk-")
+    } else {
+        config.tmp_prefix.as_deref().unwrap_or("tmp-")
+    };
+
+    // The branch of a clone is used when there is no project name.
+    let suffix = project_name
+        .map(|name| format!("-{name}"))
+        .or_else(|| {
+            cli.branch
+                .as_deref()
+                .filter(|_| !cli.worktree())
+                .map(|branch| format!("-{}", branch.replace('/', "-")))
+        })
+        .unwrap_or_default();
+
+    (prefix, suffix)
+}
+
+/// Returns the command creating the project in `tmp_dir_path` and the error message to use if it
+/// fails.
+fn setup_command(cli: &Cli, config: &Config, tmp_dir_path: &Path) -> (Command, &'static str) {
+    if cli.worktree() {
+        // The path is relative to the repository with `--repo`.
+        let tmp_dir_path =
+            std::path::absolute(tmp_dir_path).unwrap_or_else(|_| tmp_dir_path.to_path_buf());
+
+        let mut command = worktree_git_command(cli);
+        command.args(["worktree", "add"]);
+
+        if let Some(new_branch) = cli.worktree_new.as_deref() {
+            command.arg("-b").arg(new_branch).arg(tmp_dir_path);
+        } else if let Some(commit) = cli.worktree_at.as_deref() {
+            command.arg("-d").arg(tmp_dir_path).arg(commit);
+        } else if let Some(Some(branch)) = cli.worktree_branch.as_ref() {
+            command.arg(tmp_dir_path).arg(branch);
+        } else {
+            command.arg("-d").arg(tmp_dir_path);
+        }
+
+        (command, "cannot create working tree")
+    } else if let Some(url) = &cli.git {
+        // Cloning in the current directory works with every version of git even though the
+        // temporary directory already exists, a local repository must then be absolute.
+        let local_path = Path::new(url).exists();
+        let url = if local_path {
+            std::path::absolute(url)
+                .map(|x| x.into_os_string())
+                .unwrap_or_else(|_| url.into())
+        } else {
+            url.into()
+        };
+
+        let mut command = Command::new("git");
+        command
+            .current_dir(tmp_dir_path)
+            .arg("clone")
+            .arg(url)
+            .arg(".");
+
+        if let Some(branch) = cli.branch.as_deref() {
+            command.args(["--branch", branch, "--single-branch"]);
+        }
+
+        // The depth is ignored by git when cloning a local path.
+        match config.git_repo_depth {
+            _ if local_path => {}
+            Some(Depth::Active(false)) => {}
+            None | Some(Depth::Active(true)) => {
+                command.arg("--depth").arg("1");
+            }
+            Some(Depth::Level(level)) => {
+                command.arg("--depth").arg(level.to_string());
+            }
+        };
+
+        // Combined with the depth, only the commits within the depth are fetched, without the
+        // filtered objects.
+        if let Some(filter) = cli.filter.as_deref().or(config.git_clone_filter.as_deref()) {
+            command.arg(format!("--filter={filter}"));
+        }
+
+        if !cli.sparse.is_empty() {
+            command.arg("--sparse");
+        }
+
+        if cli.recurse_submodules || config.git_clone_submodules {
+            command.arg("--recurse-submodules");
+
+            if !matches!(config.git_repo_depth, Some(Depth::Active(false))) {
+                command.arg("--shallow-submodules");
+            }
+        }
+
+        (command, "cannot clone repository")
+    } else {
+        let crate_name = cli
+            .bin_name
+            .clone()
+            .or_else(|| cli.project_name.clone())
+            .unwrap_or_else(|| {
+                tmp_dir_path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_lowercase()
+            });
+
+        let mut command = Command::new("cargo");
+        command
+            .current_dir(tmp_dir_path)
+            .args(["init", "--name", crate_name.as_str()]);
+
+        if is_offline() {
+            command.arg("--offline");
+        }
+
+        // Cargo's default crate type is not relied upon.
+        if cli.lib || cli.lib_with_bin {
+            command.arg("--lib");
+        } else {
+            command.arg("--bin");
+        }
+
+        if cli.no_git {
+            command.args(["--vcs", "none"]);
+        } else if let Some(arg) = cli.vcs.as_deref().or(config.vcs.as_deref()) {
+            command.args(["--vcs", arg]);
+        }
+
+        if let Some(edition) = cli.edition.as_deref() {
+            if edition.len() == 2 && edition.bytes().all(|x| x.is_ascii_digit()) {
+                command.arg("--edition").arg(format!("20{edition}"));
+            } else {
+                command.args(["--edition", edition]);
+            }
+        }
+
+        (command, "cargo command failed")
+    }
+}
+
+/// Check out a revision of a cloned repository, the revision is fetched if the clone doesn't
+/// contain it.
+fn checkout_rev(tmp_dir: &Path, rev: &str, shallow: bool) -> Result<()> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(tmp_dir)
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .status()
+            .context("Could not start git")
+    };
+
+    let target = if git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{rev}^{{commit}}"),
+    ])?
+    .success()
+    {
+        rev
+    } else {
+        let mut args = vec!["fetch"];
+        if shallow {
+            args.extend(["--depth", "1"]);
+        }
+        args.extend(["origin", rev]);
+
+        ensure!(
+            git(&args)?.success(),
+            "cannot find revision `{rev}` in the repository"
+        );
+
+        "FETCH_HEAD"
+    };
+
+    ensure!(
+        git(&["checkout", "--quiet", target])?.success(),
+        "cannot check out revision `{rev}`"
+    );
+
+    Ok(())
+}
+
+/// Returns the command running the `post_create` setting with the shell.
+fn post_create_command(post_create: &str) -> Command {
+    #[cfg(unix)]
+    {
+        let mut command = Command::new(env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()));
+        command.args(["-c", post_create]);
+        command
+    }
+
+    #[cfg(windows)]
+    {
+        let mut command = Command::new("cmd");
+        command.args(["/C", post_create]);
+        command
+    }
+}
+
+/// Ask a yes or no question, the default answer is yes.
+///
+/// The answer is no when cargo-temp is interrupted, which keeps the project and the branch.
+fn confirm(question: &str) -> bool {
+    println!("{question} (Y/n)");
+
+    let mut input = String::new();
+
+    loop {
+        let res = stdin().read_line(&mut input);
+
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            break false;
+        }
+
+        match res {
+            Ok(_n) => match input.trim() {
+                "" | "Yes" | "yes" | "Y" | "y" => {
+                    break true;
+                }
+                "No" | "no" | "N" | "n" => {
+                    break false;
+                }
+                _ => println!("hmm, `{}` doesn't look like `yes` or `no`", input.trim()),
+            },
+            Err(err) => {
+                log::error!("failed to read input: {}", err);
+            }
+        }
+
+        input.clear()
+    }
+}
+
+/// Returns a git command running in the repository of the working tree.
+fn worktree_git_command(cli: &Cli) -> Command {
+    let mut command = Command::new("git");
+
+    if let Some(repo) = cli.repo.as_deref() {
+        command.arg("-C").arg(repo);
+    }
+
+    command
+}
+
+fn add_dependencies_to_project(
+    tmp_dir: &Path,
+    dependencies: &[Dependency],
+    verbose: bool,
+    use_cargo_add: bool,
+    wrap_width: usize,
+) -> Result<()> {
+    if dependencies.is_empty() {
+        return Ok(());
+    }
+
+    if verbose {
+        for dependency in dependencies {
+            log::info!(
+                "Adding to [{}]: {}",
+                dependency.kind().table(),
+                format_dependency(dependency, wrap_width)
+            );
+        }
+    }
+
+    if use_cargo_add {
+        for dependency in dependencies {
+            let mut command = cargo_add_command(tmp_dir, dependency);
+
+            ensure!(
+                command.status().context("Could not start cargo")?.success(),
+                "cannot add dependency `{}`",
+                dependency.name()
+            );
+        }
+
+        return Ok(());
+    }
+
+    let mut toml = OpenOptions::new()
+        .append(true)
+        .open(tmp_dir.join("Cargo.toml"))?;
+
+    write_dependencies(&mut toml, dependencies, wrap_width)
+}
+
+fn cargo_add_command(tmp_dir: &Path, dependency: &Dependency) -> Command {
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(tmp_dir)
+        .arg("add")
+        .args(cargo_add_args(dependency));
+
+    command
+}
+
+fn write_dependencies(
+    out: &mut impl Write,
+    dependencies: &[Dependency],
+    wrap_width: usize,
+) -> Result<()> {
+    // `cargo init` leaves `[dependencies]` as the last table of the manifest, other kinds of
+    // dependencies need their own table.
+    for kind in [
+        DependencyKind::Normal,
+        DependencyKind::Dev,
+        DependencyKind::Build,
+    ] {
+        let mut dependencies = dependencies.iter().filter(|x| x.kind() == kind).peekable();
+
+        if dependencies.peek().is_none() {
+            continue;
+        }
+
+        if kind != DependencyKind::Normal {
+            writeln!(out, "\n[{}]", kind.table())?;
+        }
+
+        for dependency in dependencies {
+            writeln!(out, "{}", format_dependency(dependency, wrap_width))?
+        }
+    }
+
+    Ok(())
+}
+
+const STRICT_LINTS: &str = r#"
+[rust]
+unsafe_code = "forbid"
+rust_2018_idioms = { level = "warn", priority = -1 }
+
+[clippy]
+all = { level = "deny", priority = -1 }
+pedantic = { level = "warn", priority = -1 }
+"#;
+
+/// Returns the lints of a preset, the presets of the config file win over the built-in ones.
+fn lints_preset(config: &Config, name: &str) -> Result<toml::Table> {
+    if let Some(lints) = config.lints.get(name) {
+        return Ok(lints.clone());
+    }
+
+    ensure!(name == "strict", "cannot find lints preset `{name}`");
+
+    Ok(STRICT_LINTS.parse().expect("invalid built-in lints preset"))
+}
+
+/// Merge the lints in the `[lints]` table of the manifest, the lint levels already set are
+/// replaced.
+fn add_lints(manifest: &str, lints: &toml::Table) -> Result<String> {
+    let mut manifest = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .context("cannot parse Cargo.toml")?;
+
+    // A virtual manifest can only share its lints with the members of the workspace.
+    let root: &mut dyn toml_edit::TableLike =
+        if !manifest.contains_key("package") && manifest.contains_key("workspace") {
+            manifest["workspace"]
+                .as_table_like_mut()
+                .context("`workspace` is not a table in Cargo.toml")?
+        } else {
+            manifest.as_table_mut()
+        };
+
+    let manifest_lints = root
+        .entry("lints")
+        .or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        })
+        .as_table_like_mut()
+        .context("`lints` is not a table in Cargo.toml")?;
+
+    ensure!(
+        !manifest_lints.contains_key("workspace"),
+        "cannot add lints, the project inherits the lints of its workspace"
+    );
+
+    for (tool, levels) in lints {
+        let levels = levels
+            .as_table()
+            .with_context(|| format!("`{tool}` lints are not a table"))?;
+        let manifest_levels = manifest_lints
+            .entry(tool)
+            .or_insert(toml_edit::table())
+            .as_table_like_mut()
+            .with_context(|| format!("`lints.{tool}` is not a table in Cargo.toml"))?;
+
+        for (lint, level) in levels {
+            let level = level
+                .to_string()
+                .parse::<toml_edit::Value>()
+                .with_context(|| format!("invalid level for lint `{lint}`"))?;
+            manifest_levels.insert(lint, toml_edit::value(level));
+        }
+    }
+
+    Ok(manifest.to_string())
+}
+
+/// Merge the `extra_manifest` setting into the manifest, its tables are merged with the existing
+/// ones (like `[dependencies]`) and its arrays of tables (like `[[bin]]`) are appended.
+fn merge_manifest(manifest: &str, extra_manifest: &str) -> Result<String> {
+    let mut manifest = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .context("cannot parse Cargo.toml")?;
+    let extra_manifest = extra_manifest
+        .parse::<toml_edit::DocumentMut>()
+        .context("cannot parse the `extra_manifest` setting")?;
+
+    // The new tables are written after the existing ones.
+    let mut position = last_position(manifest.as_item()) + 1;
+    merge_table(
+        manifest.as_table_mut(),
+        extra_manifest.as_table(),
+        "",
+        &mut position,
+    )?;
+
+    Ok(manifest.to_string())
+}
+
+fn merge_table(
+    table: &mut dyn toml_edit::TableLike,
+    extra: &dyn toml_edit::TableLike,
+    path: &str,
+    position: &mut usize,
+) -> Result<()> {
+    for (key, item) in extra.iter() {
+        let path = if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        };
+
+        let Some(existing) = table.get_mut(key) else {
+            let mut item = item.clone();
+            set_positions(&mut item, position);
+            table.insert(key, item);
+            continue;
+        };
+
+        match (existing, item) {
+            (toml_edit::Item::ArrayOfTables(existing), toml_edit::Item::ArrayOfTables(extra)) => {
+                for extra in extra.iter() {
+                    let mut extra = extra.clone();
+                    set_table_positions(&mut extra, position);
+                    existing.push(extra);
+                }
+            }
+            (existing, item) if existing.is_table_like() && item.is_table_like() => merge_table(
+                existing.as_table_like_mut().unwrap(),
+                item.as_table_like().unwrap(),
+                &path,
+                position,
+            )?,
+            _ => bail!("`{path}` of the `extra_manifest` setting is already set in Cargo.toml"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the highest position of the tables in the document.
+fn last_position(item: &toml_edit::Item) -> usize {
+    match item {
+        toml_edit::Item::Table(table) => table
+            .iter()
+            .map(|(_, item)| last_position(item))
+            .chain(table.position())
+            .max()
+            .unwrap_or(0),
+        toml_edit::Item::ArrayOfTables(tables) => tables
+            .iter()
+            .flat_map(|table| {
+                table
+                    .iter()
+                    .map(|(_, item)| last_position(item))
+                    .chain(table.position())
+            })
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Number the tables of an item from `position`, in the order they are written.
+fn set_positions(item: &mut toml_edit::Item, position: &mut usize) {
+    match item {
+        toml_edit::Item::Table(table) => set_table_positions(table, position),
+        toml_edit::Item::ArrayOfTables(tables) => {
+            for table in tables.iter_mut() {
+                set_table_positions(table, position);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn set_table_positions(table: &mut toml_edit::Table, position: &mut usize) {
+    table.set_position(*position);
+    *position += 1;
+
+    for (_, item) in table.iter_mut() {
+        set_positions(item, position);
+    }
+}
+
+/// Set the settings of a `[profile.<name>]` table of the manifest, the table is merged with an
+/// existing one and the settings already set (by `extra_manifest` for example) are kept.
+fn set_profile(
+    manifest: &str,
+    profile: &str,
+    settings: &[(&str, toml_edit::Value)],
+) -> Result<String> {
+    let mut manifest = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .context("cannot parse Cargo.toml")?;
+
+    let profiles = manifest
+        .entry("profile")
+        .or_insert_with(|| {
+            let mut table = toml_edit::Table::new();
+            table.set_implicit(true);
+            toml_edit::Item::Table(table)
+        })
+        .as_table_like_mut()
+        .context("`profile` is not a table in Cargo.toml")?;
+    let table = profiles
+        .entry(profile)
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .with_context(|| format!("`profile.{profile}` is not a table in Cargo.toml"))?;
+
+    for (key, value) in settings {
+        if !table.contains_key(key) {
+            table.insert(key, toml_edit::value(value.clone()));
+        }
+    }
+
+    Ok(manifest.to_string())
+}
+
+/// Set the `description` field of the `[package]` table of the manifest.
+fn set_description(manifest: &str, description: &str) -> Result<String> {
+    let mut manifest = manifest
+        .parse::<toml_edit::DocumentMut>()
+        .context("cannot parse Cargo.toml")?;
+
+    let package = manifest
+        .get_mut("package")
+        .context("cannot set the description of a virtual manifest")?
+        .as_table_like_mut()
+        .context("`package` is not a table in Cargo.toml")?;
+    package.insert("description", toml_edit::value(description));
+
+    Ok(manifest.to_string())
+}
+
+fn ensure_not_interrupted() -> Result<()> {
+    ensure!(!INTERRUPTED.load(Ordering::SeqCst), "interrupted");
+    Ok(())
+}
+
+/// Returns `true` if the repository can be cloned without accessing the network.
+fn is_local_repository(url: &str) -> bool {
+    url.starts_with("file://") || Path::new(url).exists()
+}
+
+/// Returns `true` when cargo is configured to run without accessing the network.
+pub fn is_offline() -> bool {
+    env::var("CARGO_NET_OFFLINE").is_ok_and(|x| x == "true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// Returns the default config with the projects in `temporary_project_dir`.
+    fn test_config(temporary_project_dir: &Path) -> Config {
+        let mut config = Config::new().unwrap();
+        config.temporary_project_dir = temporary_project_dir.to_path_buf();
+        config
+    }
+
+    fn setup_args(args: &[&str]) -> Vec<String> {
+        let cli = Cli::parse_from(["cargo-temp"].iter().chain(args));
+        let config = Config::new().unwrap();
+        let (command, _) = setup_command(&cli, &config, Path::new("tmp-test"));
+
+        command
+            .get_args()
+            .map(|x| x.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn init_bin() {
+        let args = setup_args(&[]);
+
+        assert!(args.contains(&"--bin".to_string()), "{args:?}");
+        assert!(!args.contains(&"--lib".to_string()), "{args:?}");
+    }
+
+    #[test]
+    fn init_lib() {
+        let args = setup_args(&["--lib"]);
+
+        assert!(args.contains(&"--lib".to_string()), "{args:?}");
+        assert!(!args.contains(&"--bin".to_string()), "{args:?}");
+    }
+
+    #[test]
+    fn init_lib_with_bin() {
+        let args = setup_args(&["--lib-with-bin"]);
+
+        assert!(args.contains(&"--lib".to_string()), "{args:?}");
+        assert!(!args.contains(&"--bin".to_string()), "{args:?}");
+        assert!(lib_with_bin_main("tmp-abc123").contains("\nuse tmp_abc123::*;\n"));
+    }
+
+    #[test]
+    fn scaffold_paths() {
+        assert!(is_inside_project(Path::new("src/routes.rs")));
+        assert!(is_inside_project(Path::new("./static/index.html")));
+        assert!(!is_inside_project(Path::new("")));
+        assert!(!is_inside_project(Path::new(".")));
+        assert!(!is_inside_project(Path::new("../outside.rs")));
+        assert!(!is_inside_project(Path::new("src/../../outside.rs")));
+        assert!(!is_inside_project(Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    fn init_bin_name() {
+        let name_arg = |args: &[&str]| {
+            let args = setup_args(args);
+            let i = args.iter().position(|x| x == "--name").unwrap();
+            args[i + 1].clone()
+        };
+
+        assert_eq!(name_arg(&[]), "tmp-test");
+        assert_eq!(name_arg(&["--name", "sandbox"]), "sandbox");
+        assert_eq!(name_arg(&["--bin-name", "foo"]), "foo");
+        assert_eq!(name_arg(&["--name", "sandbox", "--bin-name", "foo"]), "foo");
+        assert!(Cli::try_parse_from(["cargo-temp", "--lib", "--bin-name", "foo"]).is_err());
+    }
+
+    #[test]
+    fn clone_branch() {
+        let args = setup_args(&[
+            "--git",
+            "https://github.com/yozhgoor/cargo-temp",
+            "--branch",
+            "a/b",
+        ]);
+        assert!(
+            args.windows(3)
+                .any(|x| x == ["--branch", "a/b", "--single-branch"]),
+            "{args:?}"
+        );
+
+        let cli = Cli::parse_from(["cargo-temp", "--git", "url", "--branch", "a/b"]);
+        let config = Config::new().unwrap();
+        assert_eq!(affixes(&cli, &config, None).1, "-a-b");
+        assert_eq!(affixes(&cli, &config, Some("foo")).1, "-foo");
+    }
+
+    #[test]
+    fn clone_into_temporary_project_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            assert!(Command::new("git")
+                .current_dir(dir.path())
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .status()
+                .unwrap()
+                .success());
+        };
+        git(&["init", "-q", "source"]);
+        write(dir.path().join("source").join("lib.rs"), "").unwrap();
+        git(&["-C", "source", "add", "lib.rs"]);
+        git(&["-C", "source", "commit", "-q", "-m", "init"]);
+        git(&["clone", "-q", "--bare", "source", "bare.git"]);
+
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("tmp-")
+            .suffix("-name")
+            .tempdir_in(dir.path())
+            .unwrap();
+        let bare = dir.path().join("bare.git");
+        let cli = Cli::parse_from(["cargo-temp", "--git", bare.to_str().unwrap()]);
+        let (mut command, _) = setup_command(&cli, &Config::new().unwrap(), tmp_dir.path());
+
+        assert!(command.status().unwrap().success());
+        assert!(tmp_dir.path().join("lib.rs").exists());
+    }
+
+    #[test]
+    fn local_repository() {
+        assert!(is_local_repository("file:///srv/git/cargo-temp.git"));
+        assert!(is_local_repository(env!("CARGO_MANIFEST_DIR")));
+        assert!(!is_local_repository(
+            "https://github.com/yozhgoor/cargo-temp"
+        ));
+
+        let args = setup_args(&["--git", env!("CARGO_MANIFEST_DIR")]);
+        assert!(!args.contains(&"--depth".to_string()), "{args:?}");
+    }
+
+    #[test]
+    fn clone_submodules() {
+        let args = setup_args(&["--git", "https://github.com/gfx-rs/wgpu"]);
+        assert!(
+            !args.contains(&"--recurse-submodules".to_string()),
+            "{args:?}"
+        );
+
+        let args = setup_args(&[
+            "--git",
+            "https://github.com/gfx-rs/wgpu",
+            "--recurse-submodules",
+        ]);
+        assert!(
+            args.ends_with(&["--recurse-submodules", "--shallow-submodules"].map(String::from)),
+            "{args:?}"
+        );
+    }
+
+    #[test]
+    fn partial_clone() {
+        let args = setup_args(&[
+            "--git",
+            "https://github.com/mozilla/gecko-dev",
+            "--filter",
+            "blob:none",
+            "--sparse",
+            "js",
+        ]);
+        assert!(args.contains(&"--filter=blob:none".to_string()), "{args:?}");
+        assert!(args.contains(&"--sparse".to_string()), "{args:?}");
+        assert!(args.windows(2).any(|x| x == ["--depth", "1"]), "{args:?}");
+    }
+
+    #[test]
+    fn worktree_new_branch() {
+        let args = setup_args(&["--worktree-new", "fix-123"]);
+        assert_eq!(args[..4], ["worktree", "add", "-b", "fix-123"], "{args:?}");
+        assert!(Cli::try_parse_from(["cargo-temp", "-w", "--worktree-new", "fix-123"]).is_err());
+    }
+
+    #[test]
+    fn worktree_at_commit() {
+        let args = setup_args(&["--worktree-at", "7e0f77a"]);
+        assert_eq!(args[..3], ["worktree", "add", "-d"], "{args:?}");
+        assert_eq!(args.last().map(String::as_str), Some("7e0f77a"), "{args:?}");
+        assert!(Cli::try_parse_from(["cargo-temp", "-w", "--worktree-at", "7e0f77a"]).is_err());
+
+        let cli = Cli::parse_from(["cargo-temp", "--worktree-at", "7e0f77a"]);
+        let config = Config::new().unwrap();
+        assert_eq!(affixes(&cli, &config, None).0, "wk-");
+    }
+
+    #[test]
+    fn terminal_command_placeholders() {
+        let args = expand_terminal_command(
+            "tmux new-session -s {name} -c {dir}",
+            "tmp-AbC.d/e-my_app",
+            Path::new("/tmp/with space/tmp-AbC"),
+        );
+
+        assert_eq!(
+            args,
+            [
+                "tmux",
+                "new-session",
+                "-s",
+                "tmp-AbC-d-e-my_app",
+                "-c",
+                "/tmp/with space/tmp-AbC"
+            ]
+        );
+    }
+
+    #[test]
+    fn editor_command() {
+        assert_eq!(
+            split_editor_command("code --wait", &["--new-window".to_string()]),
+            Some((
+                "code".to_string(),
+                vec!["--wait".to_string(), "--new-window".to_string()]
+            ))
+        );
+        assert_eq!(
+            split_editor_command("hx", &[]),
+            Some(("hx".to_string(), Vec::new()))
+        );
+        assert_eq!(split_editor_command(" ", &[]), None);
+
+        let cli = Cli::parse_from(["cargo-temp"]);
+        let mut config = Config::new().unwrap();
+        config.use_env_editor = true;
+        config.editor = Some("code".to_string());
+        assert_eq!(env_editor(&cli, &config), None);
+
+        config.editor = None;
+        config.terminal_command = Some("tmux new-session -s {name} -c {dir}".to_string());
+        assert_eq!(env_editor(&cli, &config), None);
+    }
+
+    #[test]
+    fn projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::new().unwrap();
+        config.worktree_prefix = Some("tree-".to_string());
+
+        for name in ["tmp-a", "tree-b", "named", "my-crate"] {
+            create_dir_all(dir.path().join(name)).unwrap();
+        }
+        write(dir.path().join("named").join("TO_DELETE"), "").unwrap();
+
+        assert!(is_project(&dir.path().join("tmp-a"), &config));
+        assert!(is_project(&dir.path().join("tree-b"), &config));
+        assert!(is_project(&dir.path().join("named"), &config));
+        assert!(!is_project(&dir.path().join("my-crate"), &config));
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(exit_code(Some(0)), ExitCode::SUCCESS);
+        assert_eq!(exit_code(Some(3)), ExitCode::from(3));
+        assert_eq!(exit_code(Some(255)), ExitCode::from(255));
+        assert_eq!(exit_code(Some(256)), ExitCode::FAILURE);
+        assert_eq!(exit_code(Some(-1)), ExitCode::FAILURE);
+        assert_eq!(exit_code(None), ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn no_std_build() {
+        assert!(Cli::try_parse_from(["cargo-temp", "--no-std", "--run"]).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+
+        let cli = Cli::parse_from(["cargo-temp", "--no-std", "--build"]);
+        let err = validate(&cli, &config).unwrap_err();
+        assert!(err.to_string().contains("`--target`"), "{err}");
+
+        config.always_build = true;
+        let cli = Cli::parse_from(["cargo-temp", "--no-std"]);
+        assert!(validate(&cli, &config).is_err());
+
+        for args in [
+            &["--no-std", "--lib"][..],
+            &["--no-std", "--target", "thumbv7em-none-eabihf"],
+        ] {
+            let cli = Cli::parse_from(["cargo-temp"].iter().chain(args));
+            assert!(validate(&cli, &config).is_ok(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn copy_conflicts() {
+        for args in [
+            ["--copy", "src/main.rs", "--code", "fn main() {}"],
+            ["--copy", "src/main.rs", "--git", "https://github.com/a/a"],
+            ["--copy", "src/main.rs", "--worktree", "main"],
+        ] {
+            let res = Cli::try_parse_from(["cargo-temp"].into_iter().chain(args));
+            assert!(res.is_err(), "{args:?}");
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+
+        let mut cli = Cli::parse_from(["cargo-temp", "--copy", "src/cli.rs"]);
+        assert!(validate(&cli, &config).is_ok());
+
+        cli.script = Some(PathBuf::from("src/main.rs"));
+        let err = validate(&cli, &config).unwrap_err();
+        assert!(
+            err.to_string().contains("already given as argument"),
+            "{err}"
+        );
+
+        for path in ["does/not/exist", "Cargo.toml"] {
+            let cli = Cli::parse_from(["cargo-temp", "--copy", path]);
+            assert!(validate(&cli, &config).is_err(), "{path}");
+        }
+    }
+
+    #[test]
+    fn open_overrides_config_editor() {
+        let mut config = Config::new().unwrap();
+        config.editor = Some("code".to_string());
+
+        let cli = Cli::parse_from([
+            "cargo-temp",
+            "--open",
+            "alacritty --working-directory {dir}",
+        ]);
+        let (command, is_editor) =
+            shell_command(&cli, &config, "tmp-test", Path::new("tmp-test")).unwrap();
+        assert_eq!(command.get_program(), "alacritty");
+        assert!(!is_editor);
+
+        let cli = Cli::parse_from(["cargo-temp"]);
+        let (command, is_editor) =
+            shell_command(&cli, &config, "tmp-test", Path::new("tmp-test")).unwrap();
+        assert_eq!(command.get_program(), "code");
+        assert!(is_editor);
+    }
+
+    #[test]
+    fn clean_env_applies_to_every_command() {
+        let mut config = Config::new().unwrap();
+        config.clean_env_path = Some("clean-path".to_string());
+
+        let has_clean_path = |command: &Command| {
+            command
+                .get_envs()
+                .any(|(key, value)| key == "PATH" && value == Some(OsStr::new("clean-path")))
+        };
+
+        for args in [
+            &["cargo-temp", "--clean-env"][..],
+            &["cargo-temp", "--clean-env", "--editor", "code"],
+            &["cargo-temp", "--clean-env", "--open", "alacritty"],
+        ] {
+            let cli = Cli::parse_from(args);
+            let (command, _) =
+                shell_command(&cli, &config, "tmp-test", Path::new("tmp-test")).unwrap();
+            assert!(has_clean_path(&command), "{args:?}");
+        }
+
+        let cli = Cli::parse_from(["cargo-temp", "--editor", "code"]);
+        let (command, _) = shell_command(&cli, &config, "tmp-test", Path::new("tmp-test")).unwrap();
+        assert!(!has_clean_path(&command));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn clean_environment() {
+        let mut command = Command::new("sh");
+        clean_env(&mut command, None);
+
+        let path = command
+            .get_envs()
+            .find(|(key, _)| *key == "PATH")
+            .and_then(|(_, value)| value)
+            .unwrap();
+        let dirs = env::split_paths(path).collect::<Vec<_>>();
+        assert!(dirs.contains(&PathBuf::from("/usr/bin")), "{dirs:?}");
+        assert!(dirs.contains(&find_program("cargo").unwrap()), "{dirs:?}");
+        assert!(command.get_envs().all(|(key, _)| key != "CARGO_PKG_NAME"));
+
+        let mut command = Command::new("sh");
+        clean_env(&mut command, Some("/opt/bin"));
+        assert!(command
+            .get_envs()
+            .any(|(key, value)| key == "PATH" && value == Some(OsStr::new("/opt/bin"))));
+    }
+
+    #[test]
+    fn login_shell() {
+        assert_eq!(login_shell_flag("/bin/bash"), Some("-l"));
+        assert_eq!(login_shell_flag("zsh"), Some("-l"));
+        assert_eq!(login_shell_flag("/usr/bin/pwsh"), Some("-Login"));
+        assert_eq!(login_shell_flag("cmd.exe"), None);
+        assert_eq!(login_shell_flag("/usr/bin/xonsh"), None);
+    }
+
+    #[test]
+    fn age() {
+        use std::time::Duration;
+
+        assert_eq!(format_age(Duration::from_secs(42)), "42s");
+        assert_eq!(format_age(Duration::from_secs(125)), "2m");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2h");
+        assert_eq!(format_age(Duration::from_secs(3 * 86400 + 5)), "3d");
+    }
+
+    #[test]
+    fn project_target_dir() {
+        let project = tempfile::tempdir().unwrap();
+        let cargo_config_dir = project.path().join(".cargo");
+        create_dir_all(&cargo_config_dir).unwrap();
+
+        write(
+            cargo_config_dir.join("config.toml"),
+            "[build]\ntarget = \"thumbv7em-none-eabihf\"\n",
+        )
+        .unwrap();
+        assert!(!has_cargo_target_dir(project.path()));
+
+        write(
+            cargo_config_dir.join("config.toml"),
+            "[build]\ntarget-dir = \"target-repo\"\n",
+        )
+        .unwrap();
+        assert!(has_cargo_target_dir(project.path()));
+    }
+
+    #[test]
+    fn missing_temporary_project_dir() {
+        let cache = tempfile::tempdir().unwrap();
+        let path = cache.path().join("home").join(".cache").join("cargo-temp");
+
+        create_temporary_project_dir(&path).unwrap();
+        assert!(path.is_dir());
+
+        // The directory already exists when creating the next project.
+        create_temporary_project_dir(&path).unwrap();
+    }
+
+    #[test]
+    fn unsafe_temporary_project_dir() {
+        let current_dir = env::current_dir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        check_temporary_project_dir(dir.path(), &current_dir).unwrap();
+        check_temporary_project_dir(&dir.path().join("missing"), &current_dir).unwrap();
+
+        create_dir_all(dir.path().join("sub")).unwrap();
+        let err = check_temporary_project_dir(&dir.path().join("sub").join(".."), dir.path())
+            .unwrap_err();
+        assert!(
+            err.to_string().ends_with("is the current directory"),
+            "{err}"
+        );
+        assert!(check_temporary_project_dir(&dir.path().join("sub"), dir.path()).is_ok());
+
+        write(dir.path().join("Cargo.toml"), "[package]\n").unwrap();
+        let err = check_temporary_project_dir(dir.path(), &current_dir).unwrap_err();
+        assert!(
+            err.to_string().ends_with("contains a `Cargo.toml`"),
+            "{err}"
+        );
+
+        assert!(check_temporary_project_dir(&current_dir.join("src"), &current_dir).is_ok());
+        assert!(check_temporary_project_dir(Path::new("."), &current_dir).is_err());
+    }
+
+    #[test]
+    fn copy_directory() {
+        let from = tempfile::tempdir().unwrap();
+        create_dir_all(from.path().join("src")).unwrap();
+        create_dir_all(from.path().join("target")).unwrap();
+        write(from.path().join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        write(from.path().join("target").join("foo"), "").unwrap();
+
+        let to = tempfile::tempdir().unwrap();
+        create_dir_all(to.path().join("src")).unwrap();
+        write(to.path().join("src").join("main.rs"), "").unwrap();
+        write(to.path().join("Cargo.toml"), "").unwrap();
+
+        copy_dir(from.path(), to.path()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(to.path().join("src").join("main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+        assert!(to.path().join("Cargo.toml").exists());
+        assert!(!to.path().join("target").exists());
+    }
+
+    #[test]
+    fn init_edition() {
+        let args = setup_args(&["--edition", "2024"]);
+        assert!(
+            args.ends_with(&["--edition".to_string(), "2024".to_string()]),
+            "{args:?}"
+        );
+
+        let args = setup_args(&["--edition", "18"]);
+        assert!(
+            args.ends_with(&["--edition".to_string(), "2018".to_string()]),
+            "{args:?}"
+        );
+    }
+
+    #[test]
+    fn lints() {
+        let lints = r#"
+            [rust]
+            unsafe_code = "forbid"
+
+            [clippy]
+            pedantic = { level = "warn", priority = -1 }
+        "#
+        .parse::<toml::Table>()
+        .unwrap();
+
+        assert_eq!(
+            add_lints("[package]\nname = \"foo\"\n\n[dependencies]\n", &lints).unwrap(),
+            "[package]\nname = \"foo\"\n\n[dependencies]\n\n\
+            [lints.clippy]\npedantic = { level = \"warn\", priority = -1 }\n\n\
+            [lints.rust]\nunsafe_code = \"forbid\"\n"
+        );
+        assert_eq!(
+            add_lints(
+                "[package]\nname = \"foo\"\n\n[lints.rust]\nunsafe_code = \"warn\"\n\
+                missing_docs = \"warn\"\n",
+                &lints
+            )
+            .unwrap(),
+            "[package]\nname = \"foo\"\n\n[lints.rust]\nunsafe_code = \"forbid\"\n\
+            missing_docs = \"warn\"\n\n\
+            [lints.clippy]\npedantic = { level = \"warn\", priority = -1 }\n"
+        );
+        assert!(add_lints(
+            "[package]\nname = \"foo\"\n\n[lints]\nworkspace = true\n",
+            &lints
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn report() {
+        let mut config = Config::new().unwrap();
+        config.extra_manifest = Some("[build-dependencies]\ncc = \"1\"".to_string());
+
+        let mut cli = Cli::parse_from(["cargo-temp"]);
+        cli.dependencies =
+            parse_dependencies(&["json:serde_json", "insta%dev"].map(String::from), false).unwrap();
+        let report = json_report(Path::new("tmp-test"), None, &cli, &config, false).unwrap();
+        assert_eq!(
+            report,
+            "{\"path\":\"tmp-test\",\"crate_name\":null,\"dependencies\":[\
+            {\"name\":\"serde_json\",\"rename\":\"json\",\"kind\":\"normal\"},\
+            {\"name\":\"insta\",\"rename\":null,\"kind\":\"dev\"},\
+            {\"name\":\"cc\",\"rename\":null,\"kind\":\"build\"}],\"preserved\":false}"
+        );
+
+        // The `extra_manifest` is not added to a clone.
+        let cli = Cli::parse_from(["cargo-temp", "--git", "https://github.com/a/a"]);
+        let report = json_report(Path::new("tmp-test"), None, &cli, &config, false).unwrap();
+        assert!(report.contains("\"dependencies\":[]"), "{report}");
+    }
+
+    #[test]
+    fn extra_manifest() {
+        let manifest = "[package]\nname = \"foo\"\n\n\
+            [dependencies]\nanyhow = \"1\"\n\n\
+            [dev-dependencies]\ninsta = \"1\"\n";
+        let extra_manifest = "[dev-dependencies]\nproptest = \"1\"\n\n\
+            [profile.dev]\nopt-level = 1\n";
+
+        let manifest = merge_manifest(manifest, extra_manifest).unwrap();
+        assert_eq!(
+            manifest.parse::<toml::Table>().unwrap(),
+            toml::toml! {
+                [package]
+                name = "foo"
+
+                [dependencies]
+                anyhow = "1"
+
+                [dev-dependencies]
+                insta = "1"
+                proptest = "1"
+
+                [profile.dev]
+                opt-level = 1
+            }
+        );
+
+        let manifest = merge_manifest(&manifest, "[[bin]]\nname = \"a\"\n").unwrap();
+        let manifest = merge_manifest(&manifest, "[[bin]]\nname = \"b\"\n").unwrap();
+        assert_eq!(
+            manifest.parse::<toml::Table>().unwrap()["bin"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let err = merge_manifest(&manifest, "[dependencies]\nanyhow = \"2\"\n").unwrap_err();
+        assert!(err.to_string().contains("`dependencies.anyhow`"), "{err}");
+        assert!(merge_manifest(&manifest, "[profile").is_err());
+    }
+
+    #[test]
+    fn profile() {
+        assert_eq!(
+            set_profile(
+                "[package]\nname = \"foo\"\n",
+                "release",
+                &[("debug", true.into())]
+            )
+            .unwrap(),
+            "[package]\nname = \"foo\"\n\n[profile.release]\ndebug = true\n"
+        );
+
+        // The profile of `extra_manifest` is merged instead of being duplicated.
+        let manifest = "[package]\nname = \"foo\"\n\n\
+            [profile.release]\nlto = true\ndebug = false\n";
+        let manifest = set_profile(manifest, "release", &[("debug", true.into())]).unwrap();
+        let manifest = set_profile(&manifest, "release", &[("panic", "abort".into())]).unwrap();
+        assert_eq!(
+            manifest,
+            "[package]\nname = \"foo\"\n\n\
+            [profile.release]\nlto = true\ndebug = false\npanic = \"abort\"\n"
+        );
+        assert!(manifest.parse::<toml::Table>().is_ok());
+
+        assert!(set_profile("profile = 1\n", "dev", &[]).is_err());
+    }
+
+    #[test]
+    fn description() {
+        assert_eq!(
+            set_description(
+                "[package]\nname = \"foo\"\nedition = \"2021\"\n\n[dependencies]\n",
+                "A \"quick\" experiment"
+            )
+            .unwrap(),
+            "[package]\nname = \"foo\"\nedition = \"2021\"\n\
+            description = 'A \"quick\" experiment'\n\n[dependencies]\n"
+        );
+        assert_eq!(
+            set_description("[package]\nname = \"foo\"\ndescription = \"old\"\n", "new").unwrap(),
+            "[package]\nname = \"foo\"\ndescription = \"new\"\n"
+        );
+        assert!(set_description("[workspace]\nmembers = []\n", "foo").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{read, read_dir, read_link, read_to_string, write},
+    path::Path,
+};
+
+/// File of the project where the snapshot taken at its creation is stored.
+pub const SNAPSHOT_FILE: &str = ".cargo-temp-snapshot.toml";
+
+/// Files of the project's root that are not part of a snapshot.
+const IGNORED: &[&str] = &[SNAPSHOT_FILE, "TO_DELETE", "TO_PRESERVE"];
+
+/// Directories that are not part of a snapshot, at any depth of the project.
+const IGNORED_DIRS: &[&str] = &["target", ".git"];
+
+/// Hashes of the files of a project, by path relative to the project's directory.
+///
+/// Symbolic links are not followed, the path they point to is hashed instead of their content.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    files: BTreeMap<String, String>,
+}
+
+impl Snapshot {
+    pub fn take(project_path: &Path) -> Result<Self> {
+        fn add_dir(files: &mut BTreeMap<String, String>, dir: &Path, prefix: &str) -> Result<()> {
+            for entry in read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().to_string();
+
+                let file_type = entry.file_type()?;
+
+                if prefix.is_empty() && IGNORED.contains(&name.as_str())
+                    || file_type.is_dir() && IGNORED_DIRS.contains(&name.as_str())
+                {
+                    continue;
+                }
+
+                let path = format!("{prefix}{name}");
+                if file_type.is_symlink() {
+                    let target = read_link(entry.path())
+                        .with_context(|| format!("cannot read {}", entry.path().display()))?;
+                    let target = target.to_string_lossy();
+                    files.insert(path, format!("{:016x}", hash(target.as_bytes())));
+                } else if file_type.is_dir() {
+                    add_dir(files, &entry.path(), &format!("{path}/"))?;
+                } else {
+                    let content = read(entry.path())
+                        .with_context(|| format!("cannot read {}", entry.path().display()))?;
+                    files.insert(path, format!("{:016x}", hash(&content)));
+                }
+            }
+
+            Ok(())
+        }
+
+        let mut files = BTreeMap::new();
+        add_dir(&mut files, project_path, "")?;
+
+        Ok(Self { files })
+    }
+
+    pub fn save(&self, project_path: &Path) -> Result<()> {
+        write(project_path.join(SNAPSHOT_FILE), toml::to_string(self)?)
+            .context("cannot write the snapshot")
+    }
+
+    pub fn load(project_path: &Path) -> Result<Self> {
+        let content = read_to_string(project_path.join(SNAPSHOT_FILE))
+            .with_context(|| format!("cannot find a snapshot in {}", project_path.display()))?;
+
+        toml::from_str(&content).context("cannot parse the snapshot")
+    }
+
+    /// Returns the changes of the files between this snapshot and a newer one.
+    pub fn diff(&self, newer: &Snapshot) -> Changes {
+        let mut changes = Changes::default();
+
+        for (path, hash) in &newer.files {
+            match self.files.get(path) {
+                None => changes.added.push(path.clone()),
+                Some(old) if old != hash => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+
+        changes.removed = self
+            .files
+            .keys()
+            .filter(|path| !newer.files.contains_key(*path))
+            .cloned()
+            .collect();
+
+        changes
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for Changes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (status, paths) in [
+            ('A', &self.added),
+            ('M', &self.modified),
+            ('D', &self.removed),
+        ] {
+            for path in paths {
+                writeln!(f, "{status} {path}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 64-bit FNV-1a hash, stable between runs unlike the hasher of the standard library.
+fn hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_file};
+
+    #[test]
+    fn changes() {
+        let project = tempfile::tempdir().unwrap();
+        let path = project.path();
+        create_dir_all(path.join("src")).unwrap();
+        create_dir_all(path.join("target")).unwrap();
+        write(path.join("Cargo.toml"), "[package]\n").unwrap();
+        write(path.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        write(path.join("TO_DELETE"), "").unwrap();
+
+        let snapshot = Snapshot::take(path).unwrap();
+        snapshot.save(path).unwrap();
+        assert_eq!(Snapshot::load(path).unwrap(), snapshot);
+        assert!(snapshot.diff(&Snapshot::take(path).unwrap()).is_empty());
+
+        write(path.join("src").join("main.rs"), "fn main() { todo!() }\n").unwrap();
+        write(path.join("src").join("lib.rs"), "").unwrap();
+        write(path.join("target").join("foo"), "").unwrap();
+        remove_file(path.join("Cargo.toml")).unwrap();
+        remove_file(path.join("TO_DELETE")).unwrap();
+
+        let changes = snapshot.diff(&Snapshot::take(path).unwrap());
+        assert_eq!(
+            changes,
+            Changes {
+                added: vec!["src/lib.rs".to_string()],
+                modified: vec!["src/main.rs".to_string()],
+                removed: vec!["Cargo.toml".to_string()],
+            }
+        );
+        assert_eq!(
+            changes.to_string(),
+            "A src/lib.rs\nM src/main.rs\nD Cargo.toml\n"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks() {
+        let project = tempfile::tempdir().unwrap();
+        let path = project.path();
+        create_dir_all(path.join("crates").join("foo").join("target")).unwrap();
+        write(
+            path.join("crates").join("foo").join("target").join("bar"),
+            "",
+        )
+        .unwrap();
+        std::os::unix::fs::symlink("/", path.join("root")).unwrap();
+        std::os::unix::fs::symlink("does-not-exist", path.join("dangling")).unwrap();
+
+        let snapshot = Snapshot::take(path).unwrap();
+        assert_eq!(
+            snapshot.files.keys().collect::<Vec<_>>(),
+            ["dangling", "root"]
+        );
+
+        remove_file(path.join("root")).unwrap();
+        std::os::unix::fs::symlink("/tmp", path.join("root")).unwrap();
+        assert_eq!(
+            snapshot.diff(&Snapshot::take(path).unwrap()).modified,
+            ["root"]
+        );
+    }
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::config::Config;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct SubProcess {
@@ -10,6 +13,8 @@ pub struct SubProcess {
     #[serde(default)]
     pub keep_on_exit: bool,
     pub working_dir: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<BTreeMap<String, String>>,
     #[cfg(unix)]
     pub stdout: Option<bool>,
     #[cfg(unix)]
@@ -19,7 +24,27 @@ pub struct SubProcess {
 }
 
 impl SubProcess {
-    pub fn spawn(&self, tmp_dir: &Path) -> Option<Child> {
+    pub fn new(command: impl Into<String>, foreground: bool) -> Self {
+        Self {
+            command: command.into(),
+            foreground,
+            keep_on_exit: false,
+            working_dir: None,
+            env: None,
+            #[cfg(unix)]
+            stdout: None,
+            #[cfg(unix)]
+            stderr: None,
+            #[cfg(windows)]
+            inherit_handles: None,
+        }
+    }
+
+    /// Spawn the subprocess, `CARGO_TEMP_PROJECT_DIR` and `CARGO_TEMP_PROJECT_NAME` are set in its
+    /// environment.
+    pub fn spawn(&self, tmp_dir: &Path, project_name: &str) -> Option<Child> {
+        let project_dir = std::path::absolute(tmp_dir).unwrap_or_else(|_| tmp_dir.to_path_buf());
+
         let mut process = {
             #[cfg(unix)]
             {
@@ -28,6 +53,9 @@ impl SubProcess {
                 process
                     .current_dir(self.working_dir.as_deref().unwrap_or(tmp_dir))
                     .args(["-c", &self.command])
+                    .env("CARGO_TEMP_PROJECT_DIR", &project_dir)
+                    .env("CARGO_TEMP_PROJECT_NAME", project_name)
+                    .envs(self.env.iter().flatten())
                     .stdin(std::process::Stdio::null());
 
                 if !self.foreground {
@@ -53,7 +81,14 @@ impl SubProcess {
             #[cfg(windows)]
             {
                 let mut process = Command::new(&self.command);
-                process.current_dir(self.working_dir.as_deref().unwrap_or(tmp_dir));
+                process
+                    .current_dir(self.working_dir.as_deref().unwrap_or(tmp_dir))
+                    .env("CARGO_TEMP_PROJECT_DIR", &project_dir)
+                    .env("CARGO_TEMP_PROJECT_NAME", project_name);
+
+                for (key, val) in self.env.iter().flatten() {
+                    process.env(key, val);
+                }
 
                 if let Some(b) = self.inherit_handles {
                     process.inherit_handles(b);
@@ -86,11 +121,67 @@ impl SubProcess {
     }
 }
 
-pub fn start_subprocesses(config: &Config, tmp_dir: &Path) -> Vec<Child> {
+/// `cargo check` running in the background, its output is kept to be reported when exiting the
+/// shell.
+pub struct BackgroundCheck {
+    child: std::process::Child,
+    output: std::fs::File,
+}
+
+impl BackgroundCheck {
+    pub fn spawn(tmp_dir: &Path, locked: bool) -> Result<Self> {
+        use anyhow::Context;
+
+        let output =
+            tempfile::tempfile().context("cannot create the output file of cargo check")?;
+
+        let child = std::process::Command::new("cargo")
+            .current_dir(tmp_dir)
+            .args(["check", "--message-format", "short"])
+            .args(locked.then_some("--locked"))
+            .stdin(std::process::Stdio::null())
+            .stdout(output.try_clone()?)
+            .stderr(output.try_clone()?)
+            .spawn()
+            .context("Could not start cargo")?;
+
+        Ok(Self { child, output })
+    }
+
+    /// Log the result of the check, the check is killed if it's still running.
+    pub fn report(mut self) {
+        use std::io::{Read, Seek};
+
+        match self.child.try_wait() {
+            Ok(Some(status)) if status.success() => log::info!("cargo check succeeded"),
+            Ok(Some(_)) => {
+                let mut output = String::new();
+                let _ = self.output.rewind();
+                let _ = self.output.read_to_string(&mut output);
+
+                let errors = output
+                    .lines()
+                    .filter(|line| line.contains("error"))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                log::error!("cargo check failed:\n{errors}");
+            }
+            Ok(None) => {
+                log::warn!("cargo check was still running");
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+            }
+            Err(err) => log::error!("cannot get the status of cargo check: {err}"),
+        }
+    }
+}
+
+pub fn start_subprocesses(config: &Config, tmp_dir: &Path, project_name: &str) -> Vec<Child> {
     config
         .subprocesses
         .iter()
-        .filter_map(|x| x.spawn(tmp_dir))
+        .filter_map(|x| x.spawn(tmp_dir, project_name))
         .collect::<Vec<Child>>()
 }
 
@@ -150,8 +241,8 @@ pub use windows::Command;
 mod windows {
     use crate::binding::{
         CloseHandle, CreateProcessW, GetExitCodeProcess, GetLastError, TerminateProcess,
-        WaitForSingleObject, BOOL, DWORD, INFINITE, PCWSTR, PDWORD, PROCESS_INFORMATION, PWSTR,
-        STARTUPINFOW, STATUS_PENDING, UINT, WAIT_OBJECT_0,
+        WaitForSingleObject, BOOL, CREATE_UNICODE_ENVIRONMENT, DWORD, INFINITE, PCWSTR, PDWORD,
+        PROCESS_INFORMATION, PVOID, PWSTR, STARTUPINFOW, STATUS_PENDING, UINT, WAIT_OBJECT_0,
     };
     use anyhow::{bail, Result};
     use std::{
@@ -166,6 +257,7 @@ mod windows {
         command: OsString,
         inherit_handles: bool,
         current_directory: Option<PathBuf>,
+        envs: Vec<(OsString, OsString)>,
     }
 
     impl Command {
@@ -174,6 +266,7 @@ mod windows {
                 command: command.into(),
                 inherit_handles: false,
                 current_directory: None,
+                envs: Vec::new(),
             }
         }
 
@@ -187,11 +280,17 @@ mod windows {
             self
         }
 
+        pub fn env(&mut self, key: impl Into<OsString>, val: impl Into<OsString>) -> &mut Self {
+            self.envs.push((key.into(), val.into()));
+            self
+        }
+
         pub fn spawn(&mut self) -> Result<Child> {
             Child::new(
                 self.command.as_ref(),
                 self.inherit_handles,
                 self.current_directory.as_deref(),
+                &self.envs,
             )
         }
 
@@ -209,11 +308,22 @@ mod windows {
             command: &OsStr,
             inherit_handles: bool,
             current_directory: Option<&Path>,
+            envs: &[(OsString, OsString)],
         ) -> Result<Self> {
             let startup_info = STARTUPINFOW::default();
             let mut process_info = PROCESS_INFORMATION::default();
 
-            let process_creation_flags = 0 as DWORD;
+            let mut process_creation_flags = 0 as DWORD;
+
+            // The environment of the process is inherited unless variables are added.
+            let mut environment_block = (!envs.is_empty()).then(|| {
+                process_creation_flags |= CREATE_UNICODE_ENVIRONMENT;
+                environment_block(envs)
+            });
+            let environment_ptr = environment_block
+                .as_mut()
+                .map(|block| block.as_mut_ptr() as PVOID)
+                .unwrap_or(null_mut());
 
             let current_directory_ptr = current_directory
                 .map(|path| {
@@ -233,7 +343,7 @@ mod windows {
                     null_mut(),
                     inherit_handles as BOOL,
                     process_creation_flags as DWORD,
-                    null_mut(),
+                    environment_ptr,
                     current_directory_ptr as PCWSTR,
                     &startup_info,
                     &mut process_info,
@@ -319,4 +429,28 @@ mod windows {
             }
         }
     }
+
+    /// Returns the environment of the current process with the variables added, as expected by
+    /// `CreateProcessW`: `KEY=VALUE` strings terminated by a null character, followed by another
+    /// null character.
+    fn environment_block(envs: &[(OsString, OsString)]) -> Vec<u16> {
+        let mut block = Vec::new();
+
+        // The names of the environment variables are case-insensitive.
+        let inherited = std::env::vars_os().filter(|(key, _)| {
+            !envs
+                .iter()
+                .any(|(added, _)| added.eq_ignore_ascii_case(key))
+        });
+
+        for (key, val) in inherited.chain(envs.iter().cloned()) {
+            block.extend(key.encode_wide());
+            block.push('=' as u16);
+            block.extend(val.encode_wide());
+            block.push(0);
+        }
+        block.push(0);
+
+        block
+    }
 }
@@ -0,0 +1,128 @@
+#[cfg(windows)]
+use anyhow::Context;
+use anyhow::Result;
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::Duration,
+};
+
+/// The latest version is fetched again when the cached one is older than this.
+const CACHE_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Warn if a newer version of cargo-temp has been published.
+///
+/// The latest version is read from a cache file. When the cache is missing or outdated, it is
+/// refreshed in the background with `cargo search` so the check never waits for the network.
+/// Errors are ignored.
+pub fn check_for_updates() {
+    let Ok(cache_file) = cache_file_path() else {
+        return;
+    };
+
+    if let Some(latest) = fs::read_to_string(&cache_file)
+        .ok()
+        .filter(|latest| is_newer(latest.trim(), env!("CARGO_PKG_VERSION")))
+    {
+        log::warn!(
+            "cargo-temp {} is available, you are using {}",
+            latest.trim(),
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+
+    let outdated = fs::metadata(&cache_file)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_none_or(|age| age > CACHE_DURATION);
+
+    if outdated {
+        std::thread::spawn(move || {
+            if let Some(latest) = fetch_latest_version() {
+                let _ = fs::write(cache_file, latest);
+            }
+        });
+    }
+}
+
+fn cache_file_path() -> Result<PathBuf> {
+    #[cfg(unix)]
+    let cache_file_path = {
+        let cache_dir = xdg::BaseDirectories::with_prefix(env!("CARGO_PKG_NAME"))?;
+        cache_dir.place_cache_file("latest_version")?
+    };
+    #[cfg(windows)]
+    let cache_file_path = {
+        let cache_dir = dirs::cache_dir()
+            .context("could not get cache directory")?
+            .join(env!("CARGO_PKG_NAME"));
+        fs::create_dir_all(&cache_dir)?;
+
+        cache_dir.join("latest_version")
+    };
+
+    Ok(cache_file_path)
+}
+
+fn fetch_latest_version() -> Option<String> {
+    let output = Command::new("cargo")
+        .args(["search", env!("CARGO_PKG_NAME"), "--limit", "1"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    parse_search_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Returns the version of cargo-temp from the output of `cargo search`.
+fn parse_search_output(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .find(|line| line.starts_with(concat!(env!("CARGO_PKG_NAME"), " = ")))?;
+    let version = line.split('"').nth(1)?;
+
+    Some(version.to_string())
+}
+
+/// Returns `true` if `latest` is a greater version than `current`, pre-releases are ignored.
+fn is_newer(latest: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Option<Vec<u64>> {
+        version.split('.').map(|x| x.parse().ok()).collect()
+    }
+
+    match (parse(latest), parse(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_output() {
+        assert_eq!(
+            parse_search_output(
+                "cargo-temp = \"0.3.1\"    # A CLI tool that allow you to create a temporary new \
+                rust project\n... and 2 crates more (use --limit N to see more)\n"
+            )
+            .as_deref(),
+            Some("0.3.1")
+        );
+        assert_eq!(parse_search_output("cargo-template = \"1.0.0\""), None);
+    }
+
+    #[test]
+    fn newer_version() {
+        assert!(is_newer("0.3.2", "0.3.1"));
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(!is_newer("0.3.1", "0.3.1"));
+        assert!(!is_newer("0.3.0", "0.3.1"));
+        assert!(!is_newer("0.4.0-beta.1", "0.3.1"));
+    }
+}